@@ -0,0 +1,648 @@
+use std::error::Error;
+
+use bellman::groth16::{Proof, VerifyingKey};
+use bellman::VerificationError;
+use blake2s_simd::Params;
+use bls12_381::{
+    multi_miller_loop, Bls12, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt,
+    Scalar as Fr,
+};
+use ff::Field;
+use rand::RngCore;
+
+// Aggregation of Groth16 proofs of one circuit into a single logarithmic-size
+// proof, following SnarkPack (Gailly, Maller, Nitulescu 2021). The prover
+// commits to the A, B and C points of n proofs, and for a random r shows that
+//   Z_AB = prod e(A_i, B_i)^(r^i)  and  Z_C = sum r^i C_i
+// are correctly formed from the committed points, with an inner pairing
+// product argument (TIPP) and a multi-exponentiation argument (MIPP) run
+// together as one GIPA recursion. The verifier then checks all n Groth16
+// equations at once:
+//   Z_AB = e(alpha, beta)^(sum r^i) e(sum r^i IC_i, gamma) e(Z_C, delta)
+
+// Encoded sizes in bytes. bls12_381 has no encoding for Gt; an element is 12
+// base field coordinates.
+pub const G1_SIZE: usize = 48;
+pub const G2_SIZE: usize = 96;
+pub const GT_SIZE: usize = 576;
+
+// Powers of two secrets s and t in both groups, g^(s^i) and g^(t^i) for
+// i < 2n, h^(s^i) and h^(t^i) for i < n, enough to aggregate up to n proofs.
+// `generate` knows the secrets, so this is only fit for measuring; SnarkPack
+// takes them from two existing powers-of-tau ceremonies.
+pub struct AggregationSrs {
+    g_s: Vec<G1Affine>,
+    g_t: Vec<G1Affine>,
+    h_s: Vec<G2Affine>,
+    h_t: Vec<G2Affine>,
+}
+
+// The part of the SRS the verifier needs
+#[derive(Clone, Copy, Debug)]
+pub struct AggregationVk {
+    g: G1Affine,
+    h: G2Affine,
+    g_s: G1Affine,
+    g_t: G1Affine,
+    h_s: G2Affine,
+    h_t: G2Affine,
+}
+
+impl AggregationSrs {
+    pub fn generate<R: RngCore>(max_proofs: usize, rng: &mut R) -> Self {
+        let (s, t) = (Fr::random(&mut *rng), Fr::random(&mut *rng));
+        let g1 = |x: Fr| {
+            powers(x, 2 * max_proofs)
+                .into_iter()
+                .map(|p| G1Affine::from(G1Affine::generator() * p))
+                .collect()
+        };
+        let g2 = |x: Fr| {
+            powers(x, max_proofs)
+                .into_iter()
+                .map(|p| G2Affine::from(G2Affine::generator() * p))
+                .collect()
+        };
+
+        AggregationSrs {
+            g_s: g1(s),
+            g_t: g1(t),
+            h_s: g2(s),
+            h_t: g2(t),
+        }
+    }
+
+    pub fn max_proofs(&self) -> usize {
+        self.h_s.len()
+    }
+
+    // Compressed size of all the powers
+    pub fn size(&self) -> usize {
+        (self.g_s.len() + self.g_t.len()) * G1_SIZE + (self.h_s.len() + self.h_t.len()) * G2_SIZE
+    }
+
+    pub fn verifier_key(&self) -> AggregationVk {
+        AggregationVk {
+            g: self.g_s[0],
+            h: self.h_s[0],
+            g_s: self.g_s[1],
+            g_t: self.g_t[1],
+            h_s: self.h_s[1],
+            h_t: self.h_t[1],
+        }
+    }
+}
+
+// A commitment to a vector: its pairing products with the keys of s and t
+pub type Commitment = (Gt, Gt);
+
+// Cross terms of one halving round. The left terms pair the right half of the
+// first vector with the left half of the second, the right terms the reverse.
+#[derive(Clone, Debug)]
+pub struct GipaRound {
+    pub com_ab: [Commitment; 2],
+    pub z_ab: [Gt; 2],
+    pub com_c: [Commitment; 2],
+    pub z_c: [G1Affine; 2],
+}
+
+#[derive(Clone, Debug)]
+pub struct AggregateProof {
+    pub com_ab: Commitment,
+    pub com_c: Commitment,
+    pub z_ab: Gt,
+    pub z_c: G1Affine,
+    pub rounds: Vec<GipaRound>,
+    // The vectors and commitment keys folded down to one element
+    pub final_a: G1Affine,
+    pub final_b: G2Affine,
+    pub final_c: G1Affine,
+    pub final_v: (G2Affine, G2Affine),
+    pub final_w: (G1Affine, G1Affine),
+    // KZG openings showing the folded keys are derived from the SRS
+    pub opening_v: (G2Affine, G2Affine),
+    pub opening_w: (G1Affine, G1Affine),
+}
+
+impl AggregateProof {
+    pub fn num_proofs(&self) -> usize {
+        1 << self.rounds.len()
+    }
+
+    // Encoded size, with compressed curve points
+    pub fn size(&self) -> usize {
+        let round = 10 * GT_SIZE + 2 * G1_SIZE;
+        5 * GT_SIZE + G1_SIZE + self.rounds.len() * round + 6 * G1_SIZE + 5 * G2_SIZE
+    }
+}
+
+// Fiat-Shamir transcript; each challenge hashes everything appended so far
+struct Transcript {
+    bytes: Vec<u8>,
+}
+
+impl Transcript {
+    fn new(inputs: &[&[Fr]]) -> Self {
+        let mut transcript = Transcript {
+            bytes: b"snarkpack".to_vec(),
+        };
+        for input in inputs.iter().flat_map(|i| i.iter()) {
+            transcript.bytes.extend(input.to_bytes());
+        }
+        transcript
+    }
+
+    fn g1(&mut self, p: &G1Affine) {
+        self.bytes.extend(p.to_compressed());
+    }
+
+    fn g2(&mut self, p: &G2Affine) {
+        self.bytes.extend(p.to_compressed());
+    }
+
+    // bls12_381 doesn't expose the bytes of Gt, but its Debug output is the
+    // hex of the canonical coordinates
+    fn gt(&mut self, p: &Gt) {
+        self.bytes.extend(format!("{:?}", p).as_bytes());
+    }
+
+    fn commitment(&mut self, (t, u): &Commitment) {
+        self.gt(t);
+        self.gt(u);
+    }
+
+    fn round(&mut self, round: &GipaRound) {
+        for i in 0..2 {
+            self.commitment(&round.com_ab[i]);
+            self.gt(&round.z_ab[i]);
+            self.commitment(&round.com_c[i]);
+            self.g1(&round.z_c[i]);
+        }
+    }
+
+    // A nonzero scalar reduced from 64 hashed bytes, so it's close to uniform
+    fn challenge(&mut self) -> Fr {
+        loop {
+            let mut wide = [0; 64];
+            for (i, half) in wide.chunks_mut(32).enumerate() {
+                let hash = Params::new()
+                    .personal(b"aggregat")
+                    .to_state()
+                    .update(&self.bytes)
+                    .update(&[i as u8])
+                    .finalize();
+                half.copy_from_slice(hash.as_bytes());
+            }
+            let challenge = Fr::from_bytes_wide(&wide);
+            self.bytes.extend(challenge.to_bytes());
+            if !bool::from(challenge.is_zero()) {
+                return challenge;
+            }
+        }
+    }
+}
+
+// 1, x, x^2, ..., x^(n-1)
+fn powers(x: Fr, n: usize) -> Vec<Fr> {
+    std::iter::successors(Some(Fr::ONE), |p| Some(p * x)).take(n).collect()
+}
+
+// prod e(g1_i, g2_i)
+fn pairing_product(g1: &[G1Affine], g2: &[G2Affine]) -> Gt {
+    let g2: Vec<G2Prepared> = g2.iter().map(|p| G2Prepared::from(*p)).collect();
+    let terms: Vec<(&G1Affine, &G2Prepared)> = g1.iter().zip(&g2).collect();
+    multi_miller_loop(&terms).final_exponentiation()
+}
+
+// Commitment to a G1 vector under the G2 keys (v_s, v_t)
+fn commit_g1(v: (&[G2Affine], &[G2Affine]), a: &[G1Affine]) -> Commitment {
+    (pairing_product(a, v.0), pairing_product(a, v.1))
+}
+
+// Commitment to a pair of vectors, A under the G2 keys v and B under the G1
+// keys w
+fn commit_pair(
+    v: (&[G2Affine], &[G2Affine]),
+    w: (&[G1Affine], &[G1Affine]),
+    a: &[G1Affine],
+    b: &[G2Affine],
+) -> Commitment {
+    (
+        pairing_product(&[a, w.0].concat(), &[v.0, b].concat()),
+        pairing_product(&[a, w.1].concat(), &[v.1, b].concat()),
+    )
+}
+
+fn msm_g1(bases: &[G1Affine], scalars: &[Fr]) -> G1Projective {
+    bases
+        .iter()
+        .zip(scalars)
+        .fold(G1Projective::identity(), |acc, (b, s)| acc + b * s)
+}
+
+fn msm_g2(bases: &[G2Affine], scalars: &[Fr]) -> G2Projective {
+    bases
+        .iter()
+        .zip(scalars)
+        .fold(G2Projective::identity(), |acc, (b, s)| acc + b * s)
+}
+
+// left_i + x right_i
+fn fold_g1(left: &[G1Affine], right: &[G1Affine], x: Fr) -> Vec<G1Affine> {
+    let points: Vec<G1Projective> = left.iter().zip(right).map(|(l, r)| l + r * x).collect();
+    let mut folded = vec![G1Affine::identity(); points.len()];
+    G1Projective::batch_normalize(&points, &mut folded);
+    folded
+}
+
+fn fold_g2(left: &[G2Affine], right: &[G2Affine], x: Fr) -> Vec<G2Affine> {
+    let points: Vec<G2Projective> = left.iter().zip(right).map(|(l, r)| l + r * x).collect();
+    let mut folded = vec![G2Affine::identity(); points.len()];
+    G2Projective::batch_normalize(&points, &mut folded);
+    folded
+}
+
+fn fold_scalars(left: &[Fr], right: &[Fr], x: Fr) -> Vec<Fr> {
+    left.iter().zip(right).map(|(l, r)| l + r * x).collect()
+}
+
+// Coefficients of prod_j (1 + y_j X^(2^(k-1-j))), which are the weights a
+// key vector ends up folded with when round j multiplies its right half by
+// y_j
+fn folding_coefficients(ys: &[Fr]) -> Vec<Fr> {
+    ys.iter().rev().fold(vec![Fr::ONE], |coefficients, y| {
+        let right: Vec<Fr> = coefficients.iter().map(|c| c * y).collect();
+        [coefficients, right].concat()
+    })
+}
+
+// The same polynomial evaluated at z, in O(k)
+fn folding_polynomial(ys: &[Fr], z: Fr) -> Fr {
+    let k = ys.len();
+    let squares = powers_of_two(z, k);
+    ys.iter()
+        .enumerate()
+        .map(|(j, y)| Fr::ONE + y * squares[k - 1 - j])
+        .product()
+}
+
+// z, z^2, z^4, ..., z^(2^(k-1))
+fn powers_of_two(z: Fr, k: usize) -> Vec<Fr> {
+    std::iter::successors(Some(z), |p| Some(p.square())).take(k).collect()
+}
+
+// Quotient of (f(X) - f(z)) / (X - z), by synthetic division
+fn kzg_quotient(coefficients: &[Fr], z: Fr) -> Vec<Fr> {
+    let mut quotient = vec![Fr::ZERO; coefficients.len() - 1];
+    let mut carry = Fr::ZERO;
+    for i in (1..coefficients.len()).rev() {
+        carry = coefficients[i] + carry * z;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+// Aggregates proofs of one circuit, each with the public inputs it was made
+// for. The number of proofs has to be a power of two, at least 2 and at most
+// what the SRS was generated for.
+pub fn aggregate_proofs(
+    srs: &AggregationSrs,
+    proofs: &[(&Proof<Bls12>, &[Fr])],
+) -> Result<AggregateProof, Box<dyn Error>> {
+    let n = proofs.len();
+    if n < 2 || !n.is_power_of_two() || n > srs.max_proofs() {
+        return Err(format!(
+            "can't aggregate {} proofs: needs a power of two between 2 and {}",
+            n,
+            srs.max_proofs()
+        )
+        .into());
+    }
+
+    let a: Vec<G1Affine> = proofs.iter().map(|(p, _)| p.a).collect();
+    let b: Vec<G2Affine> = proofs.iter().map(|(p, _)| p.b).collect();
+    let c: Vec<G1Affine> = proofs.iter().map(|(p, _)| p.c).collect();
+    let (w_s, w_t) = (&srs.g_s[n..2 * n], &srs.g_t[n..2 * n]);
+
+    let com_ab = commit_pair((&srs.h_s[..n], &srs.h_t[..n]), (w_s, w_t), &a, &b);
+    let com_c = commit_g1((&srs.h_s[..n], &srs.h_t[..n]), &c);
+
+    let inputs: Vec<&[Fr]> = proofs.iter().map(|(_, i)| *i).collect();
+    let mut transcript = Transcript::new(&inputs);
+    transcript.commitment(&com_ab);
+    transcript.commitment(&com_c);
+    let r = transcript.challenge();
+
+    // Scaling A and C by r^i and their keys by r^-i leaves the commitments
+    // unchanged, so they now commit to the vectors being combined
+    let r_powers = powers(r, n);
+    let r_inverse_powers = powers(r.invert().unwrap(), n);
+    let scale = |points: &[G1Affine]| -> Vec<G1Affine> {
+        points.iter().zip(&r_powers).map(|(p, s)| G1Affine::from(p * s)).collect()
+    };
+    let (mut a, mut c) = (scale(&a), scale(&c));
+    let scale = |keys: &[G2Affine]| -> Vec<G2Affine> {
+        keys.iter().zip(&r_inverse_powers).map(|(k, s)| G2Affine::from(k * s)).collect()
+    };
+    let (v_s, v_t) = (scale(&srs.h_s[..n]), scale(&srs.h_t[..n]));
+
+    let z_ab = pairing_product(&a, &b);
+    let z_c = G1Affine::from(c.iter().fold(G1Projective::identity(), |acc, p| acc + p));
+    transcript.gt(&z_ab);
+    transcript.g1(&z_c);
+
+    // Z_C is the inner product of C with all ones
+    let mut ones = vec![Fr::ONE; n];
+    let (mut b, mut v, mut w) = (b, (v_s.clone(), v_t.clone()), (w_s.to_vec(), w_t.to_vec()));
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (ones_l, ones_r) = ones.split_at(half);
+        let (v_s_l, v_s_r) = v.0.split_at(half);
+        let (v_t_l, v_t_r) = v.1.split_at(half);
+        let (w_s_l, w_s_r) = w.0.split_at(half);
+        let (w_t_l, w_t_r) = w.1.split_at(half);
+
+        let round = GipaRound {
+            com_ab: [
+                commit_pair((v_s_l, v_t_l), (w_s_r, w_t_r), a_r, b_l),
+                commit_pair((v_s_r, v_t_r), (w_s_l, w_t_l), a_l, b_r),
+            ],
+            z_ab: [pairing_product(a_r, b_l), pairing_product(a_l, b_r)],
+            com_c: [commit_g1((v_s_l, v_t_l), c_r), commit_g1((v_s_r, v_t_r), c_l)],
+            z_c: [
+                G1Affine::from(msm_g1(c_r, ones_l)),
+                G1Affine::from(msm_g1(c_l, ones_r)),
+            ],
+        };
+        transcript.round(&round);
+        let x = transcript.challenge();
+        let x_inverse = x.invert().unwrap();
+
+        // Folding with x on one side and 1/x on the other keeps the inner
+        // products, up to the cross terms just committed to
+        let folded_a = fold_g1(a_l, a_r, x);
+        let folded_b = fold_g2(b_l, b_r, x_inverse);
+        let folded_c = fold_g1(c_l, c_r, x);
+        let folded_ones = fold_scalars(ones_l, ones_r, x_inverse);
+        let folded_v = (fold_g2(v_s_l, v_s_r, x_inverse), fold_g2(v_t_l, v_t_r, x_inverse));
+        let folded_w = (fold_g1(w_s_l, w_s_r, x), fold_g1(w_t_l, w_t_r, x));
+
+        (a, b, c, ones, v, w) = (folded_a, folded_b, folded_c, folded_ones, folded_v, folded_w);
+        rounds.push(round);
+        challenges.push(x);
+    }
+
+    let mut proof = AggregateProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c,
+        rounds,
+        final_a: a[0],
+        final_b: b[0],
+        final_c: c[0],
+        final_v: (v.0[0], v.1[0]),
+        final_w: (w.0[0], w.1[0]),
+        opening_v: (G2Affine::identity(), G2Affine::identity()),
+        opening_w: (G1Affine::identity(), G1Affine::identity()),
+    };
+    let z = final_challenge(&mut transcript, &proof);
+
+    // The folded v key is h^f(s/r) with f built from the inverse challenges,
+    // and the scaled keys are powers of s/r, so they open f directly
+    let inverses: Vec<Fr> = challenges.iter().map(|x| x.invert().unwrap()).collect();
+    let quotient = kzg_quotient(&folding_coefficients(&inverses), z);
+    proof.opening_v = (
+        G2Affine::from(msm_g2(&v_s, &quotient)),
+        G2Affine::from(msm_g2(&v_t, &quotient)),
+    );
+
+    // The folded w key is g^(s^n f(s)), with f built from the challenges
+    let coefficients = [vec![Fr::ZERO; n], folding_coefficients(&challenges)].concat();
+    let quotient = kzg_quotient(&coefficients, z);
+    proof.opening_w = (
+        G1Affine::from(msm_g1(&srs.g_s, &quotient)),
+        G1Affine::from(msm_g1(&srs.g_t, &quotient)),
+    );
+
+    Ok(proof)
+}
+
+// The point the folded keys are opened at, bound to the final elements
+fn final_challenge(transcript: &mut Transcript, proof: &AggregateProof) -> Fr {
+    transcript.g1(&proof.final_a);
+    transcript.g2(&proof.final_b);
+    transcript.g1(&proof.final_c);
+    transcript.g2(&proof.final_v.0);
+    transcript.g2(&proof.final_v.1);
+    transcript.g1(&proof.final_w.0);
+    transcript.g1(&proof.final_w.1);
+    transcript.challenge()
+}
+
+// Checks an aggregate against the public inputs of every proof in it, in
+// order. Costs O(log n) pairings and target group exponentiations, plus
+// O(n) field operations to combine the inputs.
+pub fn verify_aggregate(
+    vk: &AggregationVk,
+    groth_vk: &VerifyingKey<Bls12>,
+    inputs: &[&[Fr]],
+    proof: &AggregateProof,
+) -> Result<(), VerificationError> {
+    let ic = &groth_vk.ic;
+    if inputs.iter().any(|i| i.len() + 1 != ic.len()) {
+        return Err(VerificationError::InvalidVerifyingKey);
+    }
+    let n = inputs.len();
+    if proof.rounds.len() >= usize::BITS as usize || n != proof.num_proofs() {
+        return Err(VerificationError::InvalidProof);
+    }
+
+    let mut transcript = Transcript::new(inputs);
+    transcript.commitment(&proof.com_ab);
+    transcript.commitment(&proof.com_c);
+    let r = transcript.challenge();
+    transcript.gt(&proof.z_ab);
+    transcript.g1(&proof.z_c);
+
+    // Fold the claimed commitments and inner products with the cross terms
+    let (mut com_ab, mut com_c) = (proof.com_ab, proof.com_c);
+    let (mut z_ab, mut z_c) = (proof.z_ab, G1Projective::from(proof.z_c));
+    let mut challenges = vec![];
+    for round in &proof.rounds {
+        transcript.round(round);
+        let x = transcript.challenge();
+        let x_inverse = x.invert().unwrap();
+        let fold = |(t, u): Commitment, [left, right]: [Commitment; 2]| {
+            (t + left.0 * x + right.0 * x_inverse, u + left.1 * x + right.1 * x_inverse)
+        };
+
+        com_ab = fold(com_ab, round.com_ab);
+        com_c = fold(com_c, round.com_c);
+        z_ab = z_ab + round.z_ab[0] * x + round.z_ab[1] * x_inverse;
+        z_c = z_c + round.z_c[0] * x + round.z_c[1] * x_inverse;
+        challenges.push(x);
+    }
+    let z = final_challenge(&mut transcript, proof);
+    let inverses: Vec<Fr> = challenges.iter().map(|x| x.invert().unwrap()).collect();
+
+    let (a, b, c) = (proof.final_a, proof.final_b, proof.final_c);
+    let (v, w) = (proof.final_v, proof.final_w);
+    let ones = inverses.iter().map(|y| Fr::ONE + y).product::<Fr>();
+
+    // The final elements match the folded claims
+    let gipa = com_ab.0 == pairing_product(&[a, w.0], &[v.0, b])
+        && com_ab.1 == pairing_product(&[a, w.1], &[v.1, b])
+        && z_ab == pairing_product(&[a], &[b])
+        && com_c == (pairing_product(&[c], &[v.0]), pairing_product(&[c], &[v.1]))
+        && z_c == c * ones;
+
+    // The folded keys are the SRS keys folded with the challenges:
+    //   e(g, v - f(z) h) = e(g^(s/r) / g^z, opening)
+    //   e(w - s^n f(z) g, h) = e(opening, h^s / h^z)
+    let (g, h) = (G1Projective::from(vk.g), G2Projective::from(vk.h));
+    let r_inverse = r.invert().unwrap();
+    let f_v = folding_polynomial(&inverses, z);
+    let f_w = powers_of_two(z, challenges.len() + 1)[challenges.len()]
+        * folding_polynomial(&challenges, z);
+    let opens_v = |key: G2Affine, power: G1Affine, opening: G2Affine| {
+        pairing_product(&[vk.g], &[G2Affine::from(key - h * f_v)])
+            == pairing_product(&[G1Affine::from(power * r_inverse - g * z)], &[opening])
+    };
+    let opens_w = |key: G1Affine, power: G2Affine, opening: G1Affine| {
+        pairing_product(&[G1Affine::from(key - g * f_w)], &[vk.h])
+            == pairing_product(&[opening], &[G2Affine::from(power - h * z)])
+    };
+    let kzg = opens_v(v.0, vk.g_s, proof.opening_v.0)
+        && opens_v(v.1, vk.g_t, proof.opening_v.1)
+        && opens_w(w.0, vk.h_s, proof.opening_w.0)
+        && opens_w(w.1, vk.h_t, proof.opening_w.1);
+
+    // The Groth16 equations of all proofs, combined with the powers of r
+    let r_powers = powers(r, n);
+    let r_sum: Fr = r_powers.iter().sum();
+    let mut input_sums = vec![Fr::ZERO; ic.len() - 1];
+    for (input, r_power) in inputs.iter().zip(&r_powers) {
+        for (sum, value) in input_sums.iter_mut().zip(input.iter()) {
+            *sum += r_power * value;
+        }
+    }
+    let ic_sum = input_sums
+        .iter()
+        .zip(&ic[1..])
+        .fold(ic[0] * r_sum, |acc, (sum, base)| acc + base * sum);
+    let groth16 = proof.z_ab
+        == pairing_product(
+            &[
+                G1Affine::from(groth_vk.alpha_g1 * r_sum),
+                G1Affine::from(ic_sum),
+                proof.z_c,
+            ],
+            &[groth_vk.beta_g2, groth_vk.gamma_g2, groth_vk.delta_g2],
+        );
+
+    if gipa && kzg && groth16 {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidProof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_utils::fibonacci_job;
+    use bellman::groth16::{create_random_proof, generate_random_parameters};
+    use rand::thread_rng;
+
+    // A proof with the public inputs it was made for
+    type Proven = (Proof<Bls12>, Vec<Fr>);
+    type Statement<'a> = (&'a Proof<Bls12>, &'a [Fr]);
+
+    fn fibonacci_proofs(count: usize) -> (VerifyingKey<Bls12>, Vec<Proven>) {
+        let blank = fibonacci_job(10, 0, 1).blank;
+        let params = generate_random_parameters::<Bls12, _, _>(blank, &mut thread_rng()).unwrap();
+
+        let proofs = (0..count as u64)
+            .map(|f1| {
+                let job = fibonacci_job(10, 0, f1);
+                let proof = create_random_proof(job.circuit, &params, &mut thread_rng()).unwrap();
+                (proof, job.public_inputs)
+            })
+            .collect();
+        (params.vk, proofs)
+    }
+
+    fn statements(proofs: &[Proven]) -> (Vec<Statement<'_>>, Vec<&[Fr]>) {
+        let pairs: Vec<Statement> = proofs.iter().map(|(p, i)| (p, i.as_slice())).collect();
+        let inputs = pairs.iter().map(|(_, i)| *i).collect();
+        (pairs, inputs)
+    }
+
+    #[test]
+    fn aggregate_of_valid_proofs_verifies() {
+        let srs = AggregationSrs::generate(8, &mut thread_rng());
+        let (vk, proofs) = fibonacci_proofs(4);
+        let (pairs, inputs) = statements(&proofs);
+
+        let aggregate = aggregate_proofs(&srs, &pairs).unwrap();
+        assert_eq!(aggregate.num_proofs(), 4);
+        assert_eq!(aggregate.rounds.len(), 2);
+        assert!(verify_aggregate(&srs.verifier_key(), &vk, &inputs, &aggregate).is_ok());
+
+        // Two rounds of cross terms on top of the fixed part
+        assert_eq!(aggregate.size(), 5 * 576 + 48 + 2 * (10 * 576 + 96) + 6 * 48 + 5 * 96);
+    }
+
+    #[test]
+    fn tampering_fails_verification() {
+        let srs = AggregationSrs::generate(4, &mut thread_rng());
+        let vk = srs.verifier_key();
+        let (groth_vk, mut proofs) = fibonacci_proofs(4);
+        let (pairs, _) = statements(&proofs);
+        let aggregate = aggregate_proofs(&srs, &pairs).unwrap();
+
+        // Claim a different output for one of the proofs
+        proofs[1].1[0] += Fr::ONE;
+        let (_, inputs) = statements(&proofs);
+        assert!(verify_aggregate(&vk, &groth_vk, &inputs, &aggregate).is_err());
+        proofs[1].1[0] -= Fr::ONE;
+        let (_, inputs) = statements(&proofs);
+
+        // Or change the aggregate itself
+        let mut bad = aggregate.clone();
+        bad.z_c = G1Affine::from(G1Projective::from(bad.z_c) + G1Affine::generator());
+        assert!(verify_aggregate(&vk, &groth_vk, &inputs, &bad).is_err());
+        let mut bad = aggregate.clone();
+        bad.opening_w.0 = G1Affine::generator();
+        assert!(verify_aggregate(&vk, &groth_vk, &inputs, &bad).is_err());
+
+        // Or aggregate a proof of another statement
+        proofs[2].0 = proofs[3].0.clone();
+        let (pairs, inputs) = statements(&proofs);
+        let swapped = aggregate_proofs(&srs, &pairs).unwrap();
+        assert!(matches!(
+            verify_aggregate(&vk, &groth_vk, &inputs, &swapped),
+            Err(VerificationError::InvalidProof)
+        ));
+        assert!(verify_aggregate(&vk, &groth_vk, &inputs[..2], &aggregate).is_err());
+    }
+
+    #[test]
+    fn unsupported_counts_are_rejected() {
+        let srs = AggregationSrs::generate(4, &mut thread_rng());
+        let (_, proofs) = fibonacci_proofs(8);
+        let (pairs, _) = statements(&proofs);
+
+        assert!(aggregate_proofs(&srs, &pairs[..1]).is_err());
+        assert!(aggregate_proofs(&srs, &pairs[..3]).is_err());
+        assert!(aggregate_proofs(&srs, &pairs).is_err());
+        assert_eq!(srs.size(), 16 * 48 + 8 * 96);
+    }
+}
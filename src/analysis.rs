@@ -0,0 +1,376 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::benchmark::Stats;
+use crate::experimentation_utils::CSV_HEADERS;
+
+// One row of a results file. Columns added in later schema versions are
+// optional, since older files such as results_v0.csv don't have them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub line: usize, // 1-based line in the file, for reporting
+    pub proof_type: String,
+    pub proof_time: f64,
+    pub verification_time: f64,
+    pub num_constraints: Option<usize>,
+    pub setup_time: Option<f64>,
+    pub synthesis_time: Option<f64>,
+    pub run_id: Option<String>, // links to the run metadata sidecar
+}
+
+pub fn read_results(path: &Path) -> Result<Vec<Record>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    Ok(parse_results(&text).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+// "fibonacci_agg8" and the like, which `aggregate` used to append to the
+// results file. They time aggregation rather than a single proof of the
+// circuit, so they'd skew the fit and the outlier checks.
+fn is_aggregate(proof_type: &str) -> bool {
+    match proof_type.rsplit_once("_agg") {
+        Some((_, size)) => !size.is_empty() && size.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
+// Parses a file written by `write_to_csv` under any of its schema versions,
+// leaving out rows of aggregated proofs
+pub fn parse_results(text: &str) -> Result<Vec<Record>, String> {
+    let mut lines = text.lines().enumerate();
+    let (_, header) = lines.next().ok_or("empty results file")?;
+    if !CSV_HEADERS.contains(&header) {
+        return Err("not a results file (unknown header)".into());
+    }
+
+    let columns: Vec<&str> = header.split(',').collect();
+    let index = |name: &str| columns.iter().position(|c| *c == name);
+    let (proof_time, verification_time) = (index("proof_time"), index("verification_time"));
+    let num_constraints = index("num_constraints");
+    let (setup_time, synthesis_time) = (index("setup_time"), index("synthesis_time"));
+    let run_id = index("run_id");
+
+    lines
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter(|(_, line)| !is_aggregate(line.split(',').next().unwrap_or("")))
+        .map(|(n, line)| {
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != columns.len() {
+                return Err(format!(
+                    "line {}: expected {} fields, found {}",
+                    n + 1,
+                    columns.len(),
+                    fields.len()
+                ));
+            }
+
+            // Empty or absent fields are None; anything else has to parse
+            fn field<T: std::str::FromStr>(
+                fields: &[&str],
+                column: Option<usize>,
+                line: usize,
+            ) -> Result<Option<T>, String> {
+                match column.map(|c| fields[c].trim()) {
+                    None | Some("") => Ok(None),
+                    Some(value) => value
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| format!("line {}: invalid value '{}'", line, value)),
+                }
+            }
+            let required = |column| {
+                field(&fields, column, n + 1)?
+                    .ok_or_else(|| format!("line {}: missing time", n + 1))
+            };
+
+            Ok(Record {
+                line: n + 1,
+                proof_type: fields[0].to_string(),
+                proof_time: required(proof_time)?,
+                verification_time: required(verification_time)?,
+                num_constraints: field(&fields, num_constraints, n + 1)?,
+                setup_time: field(&fields, setup_time, n + 1)?,
+                synthesis_time: field(&fields, synthesis_time, n + 1)?,
+                run_id: field(&fields, run_id, n + 1)?,
+            })
+        })
+        .collect()
+}
+
+// Rows of one circuit at one size
+pub struct Group<'a> {
+    pub proof_type: &'a str,
+    pub num_constraints: Option<usize>,
+    pub records: Vec<&'a Record>,
+}
+
+// Groups by proof type and constraint count, in order of first appearance
+pub fn group_records(records: &[Record]) -> Vec<Group<'_>> {
+    let mut groups: Vec<Group> = vec![];
+    for record in records {
+        let key = (record.proof_type.as_str(), record.num_constraints);
+        match groups
+            .iter_mut()
+            .find(|g| (g.proof_type, g.num_constraints) == key)
+        {
+            Some(group) => group.records.push(record),
+            None => groups.push(Group {
+                proof_type: key.0,
+                num_constraints: key.1,
+                records: vec![record],
+            }),
+        }
+    }
+    groups
+}
+
+// Prover time model: seconds = intercept + slope * f(num_constraints)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    Linear,
+    NLogN,
+}
+
+impl Model {
+    pub fn feature(self, n: f64) -> f64 {
+        match self {
+            Model::Linear => n,
+            Model::NLogN => n * n.max(1.0).log2(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Model::Linear => "a + b*n",
+            Model::NLogN => "a + b*n*log2(n)",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fit {
+    pub model: Model,
+    pub intercept: f64,
+    pub slope: f64,
+    pub r_squared: f64,
+}
+
+// Least-squares fit of `model` to (num_constraints, seconds) points. Needs at
+// least two distinct sizes.
+pub fn fit(model: Model, points: &[(f64, f64)]) -> Option<Fit> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let xs: Vec<f64> = points.iter().map(|(c, _)| model.feature(*c)).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(points).map(|(x, (_, y))| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residual: f64 = xs
+        .iter()
+        .zip(points)
+        .map(|(x, (_, y))| (y - intercept - slope * x).powi(2))
+        .sum();
+
+    Some(Fit {
+        model,
+        intercept,
+        slope,
+        r_squared: if syy == 0.0 { 1.0 } else { 1.0 - residual / syy },
+    })
+}
+
+// Linear interpolation between closest ranks of sorted values
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+// Rows of a group whose proof time is beyond Tukey's outer fences, i.e. more
+// than three interquartile ranges outside the middle half
+pub fn outliers<'a>(group: &Group<'a>) -> Vec<&'a Record> {
+    let mut times: Vec<f64> = group.records.iter().map(|r| r.proof_time).collect();
+    times.sort_by(f64::total_cmp);
+    if times.len() < 4 {
+        return vec![];
+    }
+
+    let (q1, q3) = (quantile(&times, 0.25), quantile(&times, 0.75));
+    let (low, high) = (q1 - 3.0 * (q3 - q1), q3 + 3.0 * (q3 - q1));
+
+    group
+        .records
+        .iter()
+        .copied()
+        .filter(|r| r.proof_time < low || r.proof_time > high)
+        .collect()
+}
+
+fn ms(seconds: f64) -> String {
+    format!("{:.3}ms", seconds * 1000.0)
+}
+
+fn optional_stats(values: impl Iterator<Item = Option<f64>>) -> Option<Stats> {
+    Stats::from_values(&values.flatten().collect::<Vec<_>>())
+}
+
+// Prints the comparison tables, the prover-time fits and any outliers
+pub fn print_report(records: &[Record]) {
+    let groups = group_records(records);
+
+    println!(
+        "{:<18} {:>11} {:>6} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "circuit", "constraints", "n", "prove mean", "prove med", "prove sd", "verify mean",
+        "setup mean", "synth mean"
+    );
+    for group in &groups {
+        let proof = optional_stats(group.records.iter().map(|r| Some(r.proof_time))).unwrap();
+        let verify = optional_stats(group.records.iter().map(|r| Some(r.verification_time)));
+        let setup = optional_stats(group.records.iter().map(|r| r.setup_time));
+        let synthesis = optional_stats(group.records.iter().map(|r| r.synthesis_time));
+        let mean = |s: Option<Stats>| s.map_or("-".to_string(), |s| ms(s.mean));
+
+        println!(
+            "{:<18} {:>11} {:>6} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            group.proof_type,
+            group.num_constraints.map_or("-".to_string(), |c| c.to_string()),
+            proof.count,
+            ms(proof.mean),
+            ms(proof.median),
+            ms(proof.stddev),
+            mean(verify),
+            mean(setup),
+            mean(synthesis)
+        );
+    }
+
+    let points: Vec<(f64, f64)> = records
+        .iter()
+        .filter_map(|r| Some((r.num_constraints? as f64, r.proof_time)))
+        .collect();
+    println!("\nProver time against constraint count ({} rows):", points.len());
+    let fits: Vec<Fit> = [Model::Linear, Model::NLogN]
+        .into_iter()
+        .filter_map(|model| fit(model, &points))
+        .collect();
+    for f in &fits {
+        println!(
+            "  {:<16} a = {}, b = {:.3e} s, R^2 = {:.4}",
+            f.model.name(),
+            ms(f.intercept),
+            f.slope,
+            f.r_squared
+        );
+    }
+    match fits.iter().max_by(|a, b| a.r_squared.total_cmp(&b.r_squared)) {
+        Some(best) => println!("  best fit: {}", best.model.name()),
+        None => println!("  not enough distinct constraint counts to fit"),
+    }
+
+    println!("\nOutliers (proof time beyond 3 IQR of their group):");
+    let mut found = false;
+    for group in &groups {
+        for record in outliers(group) {
+            found = true;
+            println!(
+                "  line {:>6}: {} ({} constraints) proved in {}",
+                record.line,
+                record.proof_type,
+                group.num_constraints.map_or("?".to_string(), |c| c.to_string()),
+                ms(record.proof_time)
+            );
+        }
+    }
+    if !found {
+        println!("  none");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_schema_versions_are_read() {
+        let v0 = format!("{}\npolynomial,0.05,408,192,0.02,2\n", CSV_HEADERS[0]);
+        let v2 = format!(
+            "{}\n{}\n{}\n",
+            CSV_HEADERS[2],
+            "xor,0.04,408,192,0.005,4,1,3,14,,,,,",
+            "xor,0.03,408,192,0.004,4,1,3,14,0.2,true,0.001,0.00001,3384"
+        );
+
+        let records = parse_results(&v0).unwrap();
+        assert_eq!(records[0].num_constraints, Some(2));
+        assert_eq!(records[0].setup_time, None);
+
+        let records = parse_results(&v2).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].setup_time, None);
+        assert_eq!(records[1].setup_time, Some(0.2));
+        assert_eq!(records[1].line, 3);
+
+        let mixed = format!(
+            "{}\n{}\n{}\n",
+            CSV_HEADERS[2],
+            "fibonacci,0.03,408,192,0.004,10,1,11,39,0.2,true,0.001,0.00001,3384",
+            "fibonacci_agg8,0.3,1728,3576,0.05,10,1,11,39,1.5,true,0,0,10000"
+        );
+        let records = parse_results(&mixed).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].proof_type, "fibonacci");
+
+        assert!(parse_results("a,b\n1,2\n").is_err());
+        assert!(parse_results(&format!("{}\nxor,0.04,408\n", CSV_HEADERS[0])).is_err());
+    }
+
+    #[test]
+    fn fits_recover_the_model() {
+        // 2ms + 3us per constraint
+        let points: Vec<(f64, f64)> = [16.0, 64.0, 256.0, 1024.0]
+            .iter()
+            .map(|&n| (n, 0.002 + 3e-6 * n))
+            .collect();
+
+        let linear = fit(Model::Linear, &points).unwrap();
+        assert!((linear.slope - 3e-6).abs() < 1e-12);
+        assert!((linear.intercept - 0.002).abs() < 1e-12);
+        assert!((linear.r_squared - 1.0).abs() < 1e-9);
+        assert!(fit(Model::NLogN, &points).unwrap().r_squared < linear.r_squared);
+
+        assert!(fit(Model::Linear, &[(16.0, 1.0), (16.0, 2.0)]).is_none());
+    }
+
+    #[test]
+    fn slow_rows_are_flagged() {
+        let record = |line, proof_time| Record {
+            line,
+            proof_type: "xor".into(),
+            proof_time,
+            verification_time: 0.0,
+            num_constraints: Some(4),
+            setup_time: None,
+            synthesis_time: None,
+            run_id: None,
+        };
+        let mut records: Vec<Record> =
+            (0..20).map(|i| record(i + 2, 0.040 + i as f64 * 1e-4)).collect();
+        records.push(record(22, 0.4));
+
+        let groups = group_records(&records);
+        let flagged = outliers(&groups[0]);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].line, 22);
+    }
+}
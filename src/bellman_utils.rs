@@ -1,34 +1,167 @@
 use crate::circuits::{
-    division::DivisionCircuit, 
+    division::{DivisionCircuit, IntegerDivisionCircuit},
     matrix_multiplication::MatrixMultiplication,
-    multivar_polynomial::MultiVarPolynomialCircuit,
+    multivar_polynomial::{self, MultiVarPolynomialCircuit},
     polynomial::PolynomialCircuit,
+    univariate_polynomial::{evaluate, UnivariatePolynomialCircuit},
     xor::XorCircuit,
     fibonacci::FibonacciCircuit,
+    sudoku::{solve, Grid, SudokuCircuit},
+    sha256::{digest_inputs, sha256, Sha256PreimageCircuit},
+    poseidon::{Poseidon, PoseidonPreimageCircuit},
+    merkle::{self, MerkleCircuit, MerkleTree},
+    range::RangeProofCircuit,
+    fixed::{Fixed, FixedConfig},
+    mlp::{Layer, Mlp, MlpCircuit},
 };
 
 use bellman::groth16::{
-    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    create_random_proof, prepare_verifying_key, verify_proof, Proof, VerifyingKey,
 };
-use bls12_381::{Bls12, Scalar as Fr};
-use rand::thread_rng;
+use bellman::VerificationError;
+use bls12_381::{
+    multi_miller_loop, Bls12, G1Affine, G1Projective, G2Prepared, Gt, Scalar as Fr,
+};
+use ff::{Field, PrimeField};
+use rand::{thread_rng, RngCore};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
 use std::time::Instant;
 
-use crate::experimentation_utils::{proof_to_bytes, write_to_csv};
+use crate::constraint_counter::count_constraints;
+use crate::experimentation_utils::{write_to_csv, Sample};
+use crate::param_store::ParamStore;
+use crate::run_metadata::RunMetadata;
+use crate::serialization::{encode_proof, PointEncoding, ProofEnvelope};
+use crate::witness::generate_witness;
+
+// Where the benchmark harness keeps its parameters and writes its results.
+// Without a CSV path runs aren't recorded, e.g. for warm-up runs.
+pub struct Harness {
+    pub store: ParamStore,
+    pub csv_path: Option<PathBuf>,
+    pub run: RunMetadata,
+}
+
+impl Harness {
+    // Starts a recorded run, logging its metadata next to `csv_path`
+    pub fn recording(
+        store: ParamStore,
+        csv_path: PathBuf,
+        run: RunMetadata,
+    ) -> Result<Self, Box<dyn Error>> {
+        run.record(&csv_path)?;
+
+        Ok(Harness {
+            store,
+            csv_path: Some(csv_path),
+            run,
+        })
+    }
+
+    pub fn unrecorded(store: ParamStore) -> Self {
+        Harness {
+            store,
+            csv_path: None,
+            run: RunMetadata::collect(),
+        }
+    }
+}
 
-fn generate_and_verify_proof<C: bellman::Circuit<Fr>>(
+// One provable circuit instance: the name and shape its parameters are stored
+// under, a blank copy for parameter generation, and the assigned circuit with
+// the public inputs it should verify against
+pub struct Job<C> {
+    pub name: &'static str,
+    pub shape: String,
+    pub blank: C,
+    pub circuit: C,
+    pub public_inputs: Vec<Fr>,
+}
+
+impl<C: bellman::Circuit<Fr> + Clone> Job<C> {
+    // Loads or generates the parameters, then proves, verifies and records
+    // each stage's time in the harness CSV, if it has one
+    pub fn run(self, harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+        let params_path = harness.store.params_path(self.name, &self.shape);
+
+        let start = Instant::now();
+        let (params, params_generated) = harness
+            .store
+            .load_or_generate_once(self.name, &self.shape, self.blank)?;
+        let setup_time = start.elapsed();
+        println!(
+            "Parameter {} time: {:?}",
+            if params_generated { "generation" } else { "loading" },
+            setup_time
+        );
+
+        let start = Instant::now();
+        let pvk = prepare_verifying_key(&params.vk);
+        let vk_prep_time = start.elapsed();
+        println!("Verifying key preparation time: {:?}", vk_prep_time);
+
+        let mut sample = generate_and_verify_proof(
+            self.circuit,
+            &params,
+            &pvk,
+            &self.public_inputs,
+            self.name,
+        )?;
+        sample.setup_time = setup_time.as_secs_f64();
+        sample.params_generated = params_generated;
+        sample.vk_prep_time = vk_prep_time.as_secs_f64();
+        sample.params_size = fs::metadata(&params_path)?.len();
+
+        if let Some(csv_path) = &harness.csv_path {
+            write_to_csv(csv_path, &harness.run.run_id, &sample)?;
+        }
+        Ok(sample)
+    }
+
+    // Generates fresh parameters for this shape, replacing stored ones
+    pub fn setup(self, store: &ParamStore) -> Result<(), Box<dyn Error>> {
+        store.generate(self.name, &self.shape, self.blank)?;
+        Ok(())
+    }
+
+    // Proves the assigned circuit against the stored parameters
+    pub fn prove(self, store: &ParamStore) -> Result<ProofEnvelope, Box<dyn Error>> {
+        let params = store.load_or_generate(self.name, &self.shape, self.blank)?;
+        let proof = create_random_proof(self.circuit, &params, &mut thread_rng())?;
+
+        Ok(ProofEnvelope {
+            circuit_id: ParamStore::key(self.name, &self.shape),
+            public_inputs: self.public_inputs,
+            proof,
+        })
+    }
+}
+
+// Synthesizes, proves and verifies `c`. Setup fields of the returned sample
+// are left for the caller to fill in.
+fn generate_and_verify_proof<C: bellman::Circuit<Fr> + Clone>(
     c: C,
     params: &bellman::groth16::Parameters<Bls12>,
     pvk: &bellman::groth16::PreparedVerifyingKey<Bls12>,
     public_inputs: &[Fr],
-    num_constraints: usize,
     csv_label: &str,
-) {
+) -> Result<Sample, Box<dyn Error>> {
     let rng = &mut thread_rng();
 
+    // Measure the circuit shape instead of trusting a hand-typed count
+    let metrics = count_constraints(c.clone())?;
+
+    // Witness synthesis alone; proving below repeats it
+    let start = Instant::now();
+    generate_witness(c.clone())?;
+    let synthesis_time = start.elapsed();
+    println!("Witness synthesis time: {:?}", synthesis_time);
+
     // Generate proof
     let start = Instant::now();
-    let proof = create_random_proof(c, params, rng).unwrap();
+    let proof = create_random_proof(c, params, rng)?;
     let proof_generation_time = start.elapsed();
     println!("Proof generation time: {:?}", proof_generation_time);
 
@@ -37,7 +170,7 @@ fn generate_and_verify_proof<C: bellman::Circuit<Fr>>(
     println!("Raw proof size: {} bytes", proof_size);
 
     // Method 2: Convert proof components to bytes and measure
-    let proof_bytes = proof_to_bytes(&proof);
+    let proof_bytes = encode_proof(&proof, PointEncoding::Compressed);
     println!("Serialized proof size: {} bytes", proof_bytes.len());
 
     // Print individual component sizes
@@ -50,216 +183,691 @@ fn generate_and_verify_proof<C: bellman::Circuit<Fr>>(
     let result = verify_proof(pvk, &proof, public_inputs);
     let proof_verification_time = start.elapsed();
     println!("Proof verification time: {:?}", proof_verification_time);
-    assert!(result.is_ok());
-
-    println!("Number of constraints: {} \n", num_constraints);
+    result.map_err(|e| format!("{} proof failed to verify: {}", csv_label, e))?;
+
+    println!("Number of constraints: {}", metrics.num_constraints);
+    println!("Number of public inputs: {}", metrics.num_inputs);
+    println!("Number of auxiliary variables: {}", metrics.num_aux);
+    println!(
+        "Linear combination density: {} (A: {}, B: {}, C: {}) \n",
+        metrics.density(),
+        metrics.a_terms,
+        metrics.b_terms,
+        metrics.c_terms
+    );
 
-    // Write results to CSV
-    write_to_csv(
-        "results.csv",
-        csv_label,
-        proof_generation_time.as_secs_f64(),
-        proof_size as i32,
-        proof_bytes.len() as i32,
-        proof_verification_time.as_secs_f64(),
-        num_constraints as i32,
-    )
-    .unwrap();
+    Ok(Sample {
+        proof_type: csv_label.to_string(),
+        setup_time: 0.0,
+        params_generated: false,
+        vk_prep_time: 0.0,
+        synthesis_time: synthesis_time.as_secs_f64(),
+        proof_time: proof_generation_time.as_secs_f64(),
+        verification_time: proof_verification_time.as_secs_f64(),
+        r_proof_size: proof_size,
+        s_proof_size: proof_bytes.len(),
+        params_size: 0,
+        metrics,
+    })
 }
 
-pub fn verify_polynomial() {
-    let rng = &mut thread_rng();
+pub fn polynomial_job(x: u64, y: u64) -> Job<PolynomialCircuit<Fr>> {
+    let (x, y) = (Fr::from(x), Fr::from(y));
+    let z = Fr::from(2) * x.square() + Fr::from(3) * y + Fr::from(5);
 
-    // Generate random parameters
-    let params = {
-        let c = PolynomialCircuit::<Fr> {
+    Job {
+        name: "polynomial",
+        shape: String::new(),
+        blank: PolynomialCircuit {
             x: None,
             y: None,
             z: None,
-        };
-
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+        },
+        circuit: PolynomialCircuit {
+            x: Some(x),
+            y: Some(y),
+            z: Some(z),
+        },
+        public_inputs: vec![z],
+    }
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
+pub fn verify_polynomial(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    polynomial_job(256, 729).run(harness)
+}
 
-    let c = PolynomialCircuit {
-        x: Some(Fr::from(256)),
-        y: Some(Fr::from(729)),
-        z: Some(Fr::from(133264)),
-    };
+// p(x) = z for the polynomial with the given coefficients (c_0 first)
+pub fn univariate_polynomial_job(
+    coefficients: &[u64],
+    x: u64,
+    public_coefficients: bool,
+) -> Job<UnivariatePolynomialCircuit<Fr>> {
+    assert!(!coefficients.is_empty(), "need at least one coefficient");
+    let degree = coefficients.len() - 1;
+    let coefficients: Vec<Fr> = coefficients.iter().map(|&c| Fr::from(c)).collect();
+    let x = Fr::from(x);
+    let z = evaluate(&coefficients, x);
+
+    // z comes first, followed by the coefficients if they are public
+    let mut public_inputs = vec![z];
+    if public_coefficients {
+        public_inputs.extend_from_slice(&coefficients);
+    }
+
+    let visibility = if public_coefficients { "public" } else { "private" };
+
+    Job {
+        name: "univariate_poly",
+        shape: format!("deg{}_{}", degree, visibility),
+        blank: UnivariatePolynomialCircuit {
+            degree,
+            public_coefficients,
+            coefficients: None,
+            x: None,
+            z: None,
+        },
+        circuit: UnivariatePolynomialCircuit {
+            degree,
+            public_coefficients,
+            coefficients: Some(coefficients),
+            x: Some(x),
+            z: Some(z),
+        },
+        public_inputs,
+    }
+}
 
-    generate_and_verify_proof(c, &params, &pvk, &[Fr::from(133264)], 2, "polynomial");
+// Proves p(x) = z, so the degree can be swept from the caller
+pub fn verify_univariate_polynomial(
+    harness: &Harness,
+    coefficients: &[u64],
+    x: u64,
+    public_coefficients: bool,
+) -> Result<Sample, Box<dyn Error>> {
+    univariate_polynomial_job(coefficients, x, public_coefficients).run(harness)
 }
 
-pub fn verify_matrix_multiplication() {
-    let rng = &mut thread_rng();
+// m1 * m2 for matrices of any compatible dimensions; the expected product is
+// computed here and used as the public input
+pub fn matrix_multiplication_job(
+    m1: &[Vec<u64>],
+    m2: &[Vec<u64>],
+) -> Job<MatrixMultiplication<Fr>> {
+    let rows = m1.len();
+    let inner = m2.len();
+    let cols = m2.first().map_or(0, |row| row.len());
+    assert!(
+        m1.iter().all(|row| row.len() == inner) && m2.iter().all(|row| row.len() == cols),
+        "incompatible matrix dimensions"
+    );
 
-    // Generate random parameters
-    let params = {
-        let c = MatrixMultiplication::<Fr> {
+    let to_fr = |m: &[Vec<u64>]| -> Vec<Vec<Fr>> {
+        m.iter()
+            .map(|row| row.iter().map(|&x| Fr::from(x)).collect())
+            .collect()
+    };
+    let m1 = to_fr(m1);
+    let m2 = to_fr(m2);
+
+    let matrix_result: Vec<Vec<Fr>> = (0..rows)
+        .map(|i| {
+            (0..cols)
+                .map(|j| (0..inner).map(|k| m1[i][k] * m2[k][j]).sum())
+                .collect()
+        })
+        .collect();
+
+    let public_inputs: Vec<Fr> = matrix_result.iter().flatten().copied().collect();
+
+    Job {
+        name: "mat_mul",
+        shape: format!("{}x{}x{}", rows, inner, cols),
+        blank: MatrixMultiplication {
+            rows,
+            inner,
+            cols,
             m1: None,
             m2: None,
             matrix_result: None,
-        };
+        },
+        circuit: MatrixMultiplication {
+            rows,
+            inner,
+            cols,
+            m1: Some(m1),
+            m2: Some(m2),
+            matrix_result: Some(matrix_result),
+        },
+        public_inputs,
+    }
+}
 
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+pub fn verify_matrix_multiplication(
+    harness: &Harness,
+    m1: &[Vec<u64>],
+    m2: &[Vec<u64>],
+) -> Result<Sample, Box<dyn Error>> {
+    matrix_multiplication_job(m1, m2).run(harness)
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
-
-    // let c = MatrixMultiplication {
-    //     m1: Some([[Fr::from(1), Fr::from(2)], [Fr::from(3), Fr::from(4)]]),
-    //     m2: Some([[Fr::from(5), Fr::from(6)], [Fr::from(7), Fr::from(8)]]),
-    //     matrix_result: Some([[Fr::from(19), Fr::from(22)], [Fr::from(43), Fr::from(50)]]),
-    // };
-
-    let c = MatrixMultiplication {
-        m1: Some([[Fr::from(123), Fr::from(456)], 
-              [Fr::from(789), Fr::from(101)]]),
-        m2: Some([[Fr::from(112), Fr::from(131)], 
-              [Fr::from(415), Fr::from(161)]]),
-        matrix_result: Some([[Fr::from(203016), Fr::from(89529)], 
-            [Fr::from(130283), Fr::from(119620)]]),
-    };
+// ax^3y^2 + bx^2y + cxy + d = result
+pub fn multivar_polynomial_job(
+    [x, y, a, b, c, d]: [u64; 6],
+) -> Job<MultiVarPolynomialCircuit<Fr>> {
+    let [x, y, a, b, c, d] = [x, y, a, b, c, d].map(Fr::from);
+    let blank =
+        MultiVarPolynomialCircuit::ax3y2_bx2y_cxy_d(None, None, None, None, None, None, None);
+
+    let terms: Vec<(Fr, Vec<u32>)> = [a, b, c, d]
+        .into_iter()
+        .zip(&blank.terms)
+        .map(|(coefficient, term)| (coefficient, term.exponents.clone()))
+        .collect();
+    let result = multivar_polynomial::evaluate(&[x, y], &terms);
+
+    Job {
+        name: "multivar_poly",
+        shape: String::new(),
+        circuit: MultiVarPolynomialCircuit::ax3y2_bx2y_cxy_d(
+            Some(x),
+            Some(y),
+            Some(a),
+            Some(b),
+            Some(c),
+            Some(d),
+            Some(result),
+        ),
+        blank,
+        public_inputs: vec![result],
+    }
+}
 
-    generate_and_verify_proof(
-        c,
-        &params,
-        &pvk,
-        &[Fr::from(203016), Fr::from(89529), Fr::from(130283), Fr::from(119620)],
-        20,
-        "mat_mul",
-    );
+pub fn verify_multivar_polynomial(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    multivar_polynomial_job([222, 333, 444, 555, 666, 777]).run(harness)
 }
 
-pub fn verify_multivar_polynomial() {
-    let rng = &mut thread_rng();
+// numerator / denominator as a field quotient
+pub fn division_job(numerator: u64, denominator: u64) -> Job<DivisionCircuit<Fr>> {
+    let (numerator, denominator) = (Fr::from(numerator), Fr::from(denominator));
+    // A zero denominator has no inverse; the proof for it won't verify
+    let quotient = numerator * denominator.invert().unwrap_or(Fr::ZERO);
 
-    // Generate random parameters
-    let params = {
-        let c = MultiVarPolynomialCircuit::<Fr> {
-            x: None,
-            y: None,
+    Job {
+        name: "division",
+        shape: String::new(),
+        blank: DivisionCircuit {
+            numerator: None,
+            denominator: None,
+            quotient: None,
+        },
+        circuit: DivisionCircuit {
+            numerator: Some(numerator),
+            denominator: Some(denominator),
+            quotient: Some(quotient),
+        },
+        public_inputs: vec![quotient], // expected output (quotient)
+    }
+}
+
+pub fn verify_division(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    division_job(4003859412, 45678).run(harness)
+}
+
+// numerator = quotient * denominator + remainder over the integers
+pub fn integer_division_job(
+    numerator: u64,
+    denominator: u64,
+    num_bits: usize,
+) -> Job<IntegerDivisionCircuit<Fr>> {
+    // Division by zero can't be proven; 0 remainder n fails the range check
+    let quotient = numerator.checked_div(denominator).unwrap_or(0);
+    let remainder = numerator.checked_rem(denominator).unwrap_or(numerator);
+
+    Job {
+        name: "integer_division",
+        shape: format!("{}bit", num_bits),
+        blank: IntegerDivisionCircuit {
+            numerator: None,
+            denominator: None,
+            quotient: None,
+            remainder: None,
+            num_bits,
+        },
+        circuit: IntegerDivisionCircuit {
+            numerator: Some(Fr::from(numerator)),
+            denominator: Some(Fr::from(denominator)),
+            quotient: Some(Fr::from(quotient)),
+            remainder: Some(Fr::from(remainder)),
+            num_bits,
+        },
+        public_inputs: vec![Fr::from(quotient)], // expected output (quotient)
+    }
+}
+
+pub fn verify_integer_division(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    // 4003859500 = 87654 * 45678 + 88
+    integer_division_job(4003859500, 45678, 32).run(harness)
+}
+
+pub fn xor_job(a: u64, b: u64) -> Job<XorCircuit<Fr>> {
+    let (a, b) = (Fr::from(a), Fr::from(b));
+    let result = a + b - Fr::from(2) * a * b;
+
+    Job {
+        name: "xor",
+        shape: String::new(),
+        blank: XorCircuit {
             a: None,
             b: None,
-            c: None,
-            d: None,
             result: None,
-        };
+        },
+        circuit: XorCircuit {
+            a: Some(a),
+            b: Some(b),
+            result: Some(result),
+        },
+        public_inputs: vec![result],
+    }
+}
 
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+pub fn verify_xor(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    xor_job(1, 0).run(harness)
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
+// The nth term of the sequence starting at f0, f1
+pub fn fibonacci_job(n: usize, f0: u64, f1: u64) -> Job<FibonacciCircuit<Fr>> {
+    let (f0, f1) = (Fr::from(f0), Fr::from(f1));
+    let fn_val = (2..=n).fold((f0, f1), |(prev, current), _| (current, prev + current)).1;
 
-    let c = MultiVarPolynomialCircuit {
-        x: Some(Fr::from(222)),
-        y: Some(Fr::from(333)),
-        a: Some(Fr::from(444)),
-        b: Some(Fr::from(555)),
-        c: Some(Fr::from(666)),
-        d: Some(Fr::from(777)),
-        result: Some(Fr::from(538688548680321)),
-    };
+    Job {
+        name: "fibonacci",
+        shape: format!("n{}", n),
+        blank: FibonacciCircuit {
+            f0: None,
+            f1: None,
+            fn_val: None,
+            n,
+        },
+        // Initialize the circuit with initial values for f0 and f1, and the target Fibonacci value
+        circuit: FibonacciCircuit {
+            f0: Some(f0),
+            f1: Some(f1),
+            fn_val: Some(fn_val),
+            n,
+        },
+        public_inputs: vec![fn_val], // public input is the expected nth Fibonacci number
+    }
+}
 
-    generate_and_verify_proof(
-        c,
-        &params,
-        &pvk,
-        &[Fr::from(538688548680321)],
-        10,
-        "multivar_poly",
-    );
+pub fn verify_fibonacci(harness: &Harness, n: usize) -> Result<Sample, Box<dyn Error>> {
+    fibonacci_job(n, 0, 1).run(harness)
 }
 
-pub fn verify_division() {
-    let rng = &mut thread_rng();
+// A puzzle with a unique solution, 0 for blanks
+pub const SUDOKU_PUZZLE: Grid<u8> = [
+    [5, 3, 0, 0, 7, 0, 0, 0, 0],
+    [6, 0, 0, 1, 9, 5, 0, 0, 0],
+    [0, 9, 8, 0, 0, 0, 0, 6, 0],
+    [8, 0, 0, 0, 6, 0, 0, 0, 3],
+    [4, 0, 0, 8, 0, 3, 0, 0, 1],
+    [7, 0, 0, 0, 2, 0, 0, 0, 6],
+    [0, 6, 0, 0, 0, 0, 2, 8, 0],
+    [0, 0, 0, 4, 1, 9, 0, 0, 5],
+    [0, 0, 0, 0, 8, 0, 0, 7, 9],
+];
+
+// Knowledge of a solution to `puzzle`, which is solved here
+pub fn sudoku_job(puzzle: &Grid<u8>) -> Job<SudokuCircuit<Fr>> {
+    // An unsolvable puzzle can't be proven; its blanks fail the range checks
+    let solution = solve(puzzle).unwrap_or(*puzzle);
+    let to_fr = |grid: &Grid<u8>| grid.map(|row| row.map(|d| Some(Fr::from(d as u64))));
+
+    Job {
+        name: "sudoku",
+        shape: String::new(),
+        blank: SudokuCircuit {
+            puzzle: [[None; 9]; 9],
+            solution: [[None; 9]; 9],
+        },
+        circuit: SudokuCircuit {
+            puzzle: to_fr(puzzle),
+            solution: to_fr(&solution),
+        },
+        public_inputs: puzzle.iter().flatten().map(|&d| Fr::from(d as u64)).collect(),
+    }
+}
 
-    // Generate random parameters
-    let params = {
-        let c = DivisionCircuit::<Fr> {
-            numerator: None,
-            denominator: None,
-            quotient: None,
-        };
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+pub fn verify_sudoku(harness: &Harness) -> Result<Sample, Box<dyn Error>> {
+    sudoku_job(&SUDOKU_PUZZLE).run(harness)
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
+// Knowledge of a preimage of SHA-256(preimage)
+pub fn sha256_job(preimage: &[u8]) -> Job<Sha256PreimageCircuit> {
+    let len = preimage.len();
+
+    Job {
+        name: "sha256",
+        shape: format!("{}bytes", len),
+        blank: Sha256PreimageCircuit {
+            preimage: None,
+            len,
+        },
+        circuit: Sha256PreimageCircuit {
+            preimage: Some(preimage.to_vec()),
+            len,
+        },
+        public_inputs: digest_inputs(&sha256(preimage)), // the packed digest
+    }
+}
 
-    // Provide concrete values to test (e.g., 8 / 2 = 4)
-    let c = DivisionCircuit {
-        numerator: Some(Fr::from(4003859412)),
-        denominator: Some(Fr::from(45678)),
-        quotient: Some(Fr::from(87654)),
-    };
+// A preimage of `len` bytes for benchmarking, 0, 1, 2, ... wrapping at 256
+pub fn sha256_preimage(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
 
-    generate_and_verify_proof(
-        c,
-        &params,
-        &pvk,
-        &[Fr::from(87654)], // expected output (quotient)
-        2,
-        "division",
-    );
+pub fn verify_sha256(harness: &Harness, len: usize) -> Result<Sample, Box<dyn Error>> {
+    sha256_job(&sha256_preimage(len)).run(harness)
 }
 
-pub fn verify_xor() {
-    let rng = &mut thread_rng();
+// Knowledge of a preimage of the Poseidon hash of `preimage`
+pub fn poseidon_job(preimage: &[u64]) -> Job<PoseidonPreimageCircuit<Fr>> {
+    let preimage: Vec<Fr> = preimage.iter().map(|&x| Fr::from(x)).collect();
+    let hash = Poseidon::new().hash(&preimage);
+
+    Job {
+        name: "poseidon",
+        shape: format!("{}elements", preimage.len()),
+        blank: PoseidonPreimageCircuit {
+            preimage: vec![None; preimage.len()],
+            hash: None,
+        },
+        circuit: PoseidonPreimageCircuit {
+            preimage: preimage.into_iter().map(Some).collect(),
+            hash: Some(hash),
+        },
+        public_inputs: vec![hash],
+    }
+}
 
-    // Generate random parameters
-    let params = {
-        let c = XorCircuit::<Fr> {
-            a: None,
-            b: None,
-            result: None,
-        };
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+pub fn verify_poseidon(harness: &Harness, len: u64) -> Result<Sample, Box<dyn Error>> {
+    poseidon_job(&(1..=len).collect::<Vec<_>>()).run(harness)
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
+// Membership of leaf `index` in the Poseidon tree of `depth` over `leaves`.
+// Panics if the leaves don't fit or `index` isn't one of them.
+pub fn merkle_job(
+    depth: usize,
+    leaves: &[u64],
+    index: usize,
+) -> Job<MerkleCircuit<Fr, Poseidon<Fr>>> {
+    let leaves: Vec<Fr> = leaves.iter().map(|&x| Fr::from(x)).collect();
+    let tree = MerkleTree::new(Poseidon::new(), depth, &leaves);
+    let path = tree.path(index).expect("leaf index out of range");
+
+    Job {
+        name: "merkle",
+        shape: format!("depth{}", depth),
+        blank: MerkleCircuit::blank(tree.hasher().clone(), depth),
+        circuit: MerkleCircuit::from_path(tree.hasher().clone(), &path, tree.root()),
+        public_inputs: vec![tree.root()],
+    }
+}
 
-    let c = crate::circuits::xor::XorCircuit {
-        a: Some(Fr::from(1)),
-        b: Some(Fr::from(0)),
-        result: Some(Fr::from(1)),
-    };
+// The last of up to 16 leaves in a tree of `depth`, the rest of it empty
+pub fn verify_merkle(harness: &Harness, depth: usize) -> Result<Sample, Box<dyn Error>> {
+    let leaves: Vec<u64> = (1..=16).take(merkle::capacity(depth)).collect();
+    merkle_job(depth, &leaves, leaves.len() - 1).run(harness)
+}
 
-    generate_and_verify_proof(c, &params, &pvk, &[Fr::from(1)], 2, "xor");
+// lo <= value <= hi, for ranges up to num_bits wide
+pub fn range_job(value: u128, lo: u128, hi: u128, num_bits: usize) -> Job<RangeProofCircuit<Fr>> {
+    let (lo, hi) = (Fr::from_u128(lo), Fr::from_u128(hi));
+
+    Job {
+        name: "range",
+        shape: format!("{}bit", num_bits),
+        blank: RangeProofCircuit {
+            value: None,
+            lo: None,
+            hi: None,
+            num_bits,
+        },
+        circuit: RangeProofCircuit {
+            value: Some(Fr::from_u128(value)),
+            lo: Some(lo),
+            hi: Some(hi),
+            num_bits,
+        },
+        public_inputs: vec![lo, hi],
+    }
 }
 
-pub fn verify_fibonacci(n: usize, expected_fn_val: u64) {
-    let rng = &mut thread_rng();
+// 0 <= x < 2^num_bits for an x in the upper half
+pub fn verify_range(harness: &Harness, num_bits: usize) -> Result<Sample, Box<dyn Error>> {
+    let hi = u128::MAX >> (128 - num_bits);
+    range_job(hi - hi / 4, 0, hi, num_bits).run(harness)
+}
 
-    // Generate random parameters
-    let params = {
-        let c = FibonacciCircuit::<Fr> {
-            f0: None,
-            f1: None,
-            fn_val: None,
-            n,
-        };
-        generate_random_parameters::<Bls12, _, _>(c, rng).unwrap()
-    };
+// Inference of `model` on `input`, which has to match its input width
+pub fn mlp_job(model: &Mlp, input: &[Fixed]) -> Job<MlpCircuit> {
+    let config = model.config();
+    let sizes = model.sizes();
+    // An overflowing model can't be proven; its range checks fail
+    let output = model.evaluate(input).unwrap_or_else(|| {
+        let zero = Fixed::from_raw(config, 0).unwrap();
+        vec![zero; sizes[sizes.len() - 1]]
+    });
+
+    let public_inputs = input.iter().chain(&output).map(|x| x.to_field());
+    Job {
+        name: "mlp",
+        shape: format!(
+            "{}_{}bit{}frac",
+            sizes.iter().map(|s| s.to_string()).collect::<Vec<_>>().join("x"),
+            config.total_bits(),
+            config.frac_bits()
+        ),
+        blank: MlpCircuit {
+            config,
+            sizes: sizes.clone(),
+            model: None,
+            input: None,
+        },
+        circuit: MlpCircuit {
+            config,
+            sizes,
+            model: Some(model.clone()),
+            input: Some(input.to_vec()),
+        },
+        public_inputs: public_inputs.chain(Some(model.commitment())).collect(),
+    }
+}
 
-    let pvk = prepare_verifying_key(&params.vk);
+// Salt of the generated models. Their weights follow a public pattern, so
+// there is nothing for a random salt to hide.
+pub const MLP_SALT: u128 = 0x6d6c7020626c696e64696e67;
 
-    // Initialize the circuit with initial values for f0 and f1, and the target Fibonacci value
-    let c = FibonacciCircuit {
-        f0: Some(Fr::from(0)),
-        f1: Some(Fr::from(1)),
-        fn_val: Some(Fr::from(expected_fn_val)),
-        n,
-    };
+fn mlp_value(config: FixedConfig, x: f64) -> Result<Fixed, String> {
+    Fixed::from_f64(config, x).ok_or_else(|| {
+        let (total, frac) = (config.total_bits(), config.frac_bits());
+        format!("{} doesn't fit {} bits with {} fractional ones", x, total, frac)
+    })
+}
 
-    // Call the general proof generation and verification function
-    generate_and_verify_proof(
-        c,
-        &params,
-        &pvk,
-        &[Fr::from(expected_fn_val)], // public input is the expected nth Fibonacci number
-        n, // total number of constraints is n
-        "fibonacci",
-    );
+// A model of the given layer sizes for benchmarking, with weights in
+// [-0.5, 0.5] and biases in [-0.125, 0.125] that follow a fixed pattern
+pub fn mlp_model(config: FixedConfig, sizes: &[usize], salt: u128) -> Result<Mlp, String> {
+    let value = |x: usize, scale: f64| mlp_value(config, ((x % 17) as f64 - 8.0) / 8.0 * scale);
+    let layers = sizes
+        .windows(2)
+        .enumerate()
+        .map(|(l, pair)| {
+            Ok(Layer {
+                weights: (0..pair[1])
+                    .map(|j| (0..pair[0]).map(|i| value(31 * i + 17 * j + 7 * l, 0.5)).collect())
+                    .collect::<Result<_, String>>()?,
+                biases: (0..pair[1]).map(|j| value(13 * j + l, 0.125)).collect::<Result<_, _>>()?,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Mlp::new(config, salt, layers)
+}
+
+// An input in [-1, 1) for a model of input width `n`
+pub fn mlp_input(config: FixedConfig, n: usize) -> Result<Vec<Fixed>, String> {
+    (0..n)
+        .map(|i| mlp_value(config, ((5 * i % 8) as f64 - 4.0) / 4.0))
+        .collect()
+}
+
+// Two hidden layers of `width` between 4 inputs and 2 outputs
+pub fn verify_mlp(harness: &Harness, width: usize) -> Result<Sample, Box<dyn Error>> {
+    let config = FixedConfig::new(32, 16);
+    let model = mlp_model(config, &[4, width, width, 2], MLP_SALT)?;
+    mlp_job(&model, &mlp_input(config, 4)?).run(harness)
+}
+
+// Verifying key prepared for checking many proofs of one circuit at once
+pub struct BatchVerifier<'a> {
+    vk: &'a VerifyingKey<Bls12>,
+    beta: G2Prepared,
+    neg_gamma: G2Prepared,
+    neg_delta: G2Prepared,
+}
+
+impl<'a> BatchVerifier<'a> {
+    pub fn new(vk: &'a VerifyingKey<Bls12>) -> Self {
+        BatchVerifier {
+            vk,
+            beta: G2Prepared::from(vk.beta_g2),
+            neg_gamma: G2Prepared::from(-vk.gamma_g2),
+            neg_delta: G2Prepared::from(-vk.delta_g2),
+        }
+    }
+
+    // Checks all proofs with a random linear combination of their equations
+    //   e(A_i, B_i) = e(alpha, beta) e(IC_i, gamma) e(C_i, delta)
+    // where IC_i is the input commitment. With random 128-bit r_i this becomes
+    //   prod e(r_i A_i, B_i) e(-(sum r_i) alpha, beta)
+    //     e(sum r_i IC_i, -gamma) e(sum r_i C_i, -delta) = 1
+    // so n proofs cost n + 3 Miller loops and one final exponentiation, rather
+    // than 3n and n. A single invalid proof fails the batch, except with
+    // probability about 2^-128.
+    pub fn verify<R: RngCore>(
+        &self,
+        proofs: &[(&Proof<Bls12>, &[Fr])],
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        let ic = &self.vk.ic;
+        if proofs.iter().any(|(_, inputs)| inputs.len() + 1 != ic.len()) {
+            return Err(VerificationError::InvalidVerifyingKey);
+        }
+
+        let mut r_sum = Fr::ZERO;
+        let mut input_sums = vec![Fr::ZERO; ic.len() - 1];
+        let mut c_sum = G1Projective::identity();
+        let mut scaled_a = Vec::with_capacity(proofs.len());
+
+        for (proof, inputs) in proofs {
+            let r = Fr::from_raw([rng.next_u64(), rng.next_u64(), 0, 0]);
+            r_sum += r;
+            for (sum, input) in input_sums.iter_mut().zip(inputs.iter()) {
+                *sum += r * input;
+            }
+            c_sum += proof.c * r;
+            scaled_a.push(G1Affine::from(proof.a * r));
+        }
+
+        // sum r_i IC_i, with the input terms combined before the scalar multiplications
+        let ic_sum = input_sums
+            .iter()
+            .zip(&ic[1..])
+            .fold(ic[0] * r_sum, |acc, (sum, base)| acc + base * sum);
+
+        let ic_sum = G1Affine::from(ic_sum);
+        let c_sum = G1Affine::from(c_sum);
+        let alpha_sum = G1Affine::from(-(self.vk.alpha_g1 * r_sum));
+        let b: Vec<G2Prepared> = proofs.iter().map(|(p, _)| G2Prepared::from(p.b)).collect();
+
+        let mut terms: Vec<(&G1Affine, &G2Prepared)> = scaled_a.iter().zip(&b).collect();
+        terms.push((&alpha_sum, &self.beta));
+        terms.push((&ic_sum, &self.neg_gamma));
+        terms.push((&c_sum, &self.neg_delta));
+
+        if multi_miller_loop(&terms).final_exponentiation() == Gt::identity() {
+            Ok(())
+        } else {
+            Err(VerificationError::InvalidProof)
+        }
+    }
+}
+
+// Batch-verifies proofs of one circuit against its verifying key
+pub fn batch_verify(
+    vk: &VerifyingKey<Bls12>,
+    proofs: &[(&Proof<Bls12>, &[Fr])],
+) -> Result<(), VerificationError> {
+    BatchVerifier::new(vk).verify(proofs, &mut thread_rng())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman::groth16::generate_random_parameters;
+
+    // A proof with the public inputs it was made for
+    type Proven = (Proof<Bls12>, Vec<Fr>);
+
+    fn fibonacci_proofs(count: usize) -> (VerifyingKey<Bls12>, Vec<Proven>) {
+        let blank = fibonacci_job(10, 0, 1).blank;
+        let params = generate_random_parameters::<Bls12, _, _>(blank, &mut thread_rng()).unwrap();
+
+        let proofs = (0..count as u64)
+            .map(|f1| {
+                let job = fibonacci_job(10, 0, f1);
+                let proof = create_random_proof(job.circuit, &params, &mut thread_rng()).unwrap();
+                (proof, job.public_inputs)
+            })
+            .collect();
+        (params.vk, proofs)
+    }
+
+    fn batch(proofs: &[Proven]) -> Vec<(&Proof<Bls12>, &[Fr])> {
+        proofs.iter().map(|(p, i)| (p, i.as_slice())).collect()
+    }
+
+    #[test]
+    fn valid_batch_is_accepted() {
+        let (vk, proofs) = fibonacci_proofs(8);
+        assert!(batch_verify(&vk, &batch(&proofs)).is_ok());
+        assert!(batch_verify(&vk, &batch(&proofs[..1])).is_ok());
+        assert!(batch_verify(&vk, &[]).is_ok());
+    }
+
+    #[test]
+    fn one_bad_proof_fails_the_batch() {
+        let (vk, mut proofs) = fibonacci_proofs(8);
+
+        // Claim a different output for one of the proofs
+        proofs[5].1[0] += Fr::ONE;
+        assert!(matches!(
+            batch_verify(&vk, &batch(&proofs)),
+            Err(VerificationError::InvalidProof)
+        ));
+
+        // Or swap in a proof for another statement
+        proofs[5].1[0] -= Fr::ONE;
+        proofs[2].0 = proofs[3].0.clone();
+        assert!(batch_verify(&vk, &batch(&proofs)).is_err());
+    }
+
+    #[test]
+    fn wrong_input_count_is_rejected() {
+        let (vk, proofs) = fibonacci_proofs(1);
+        let inputs = [proofs[0].1[0], Fr::ONE];
+        assert!(matches!(
+            batch_verify(&vk, &[(&proofs[0].0, &inputs)]),
+            Err(VerificationError::InvalidVerifyingKey)
+        ));
+    }
 }
@@ -0,0 +1,635 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bellman::groth16::{create_random_proof, prepare_verifying_key, verify_proof, Proof};
+use bellman::SynthesisError;
+use bls12_381::{Bls12, Scalar as Fr};
+use rand::thread_rng;
+
+use crate::aggregation::{aggregate_proofs, verify_aggregate, AggregationSrs, G1_SIZE, G2_SIZE};
+use crate::bellman_utils::*;
+use crate::experimentation_utils::{write_to_csv, Sample};
+use crate::param_store::ParamStore;
+
+// A stage name and how to read its time off a sample
+pub type Stage = (&'static str, fn(&Sample) -> f64);
+
+// The timed stages of a sample, in pipeline order
+pub const STAGES: [Stage; 5] = [
+    ("setup", |s| s.setup_time),
+    ("vk_prep", |s| s.vk_prep_time),
+    ("synthesis", |s| s.synthesis_time),
+    ("proof", |s| s.proof_time),
+    ("verification", |s| s.verification_time),
+];
+
+// Descriptive statistics of one stage's timings, in seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64, // sample standard deviation, 0 for a single value
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64, // nearest-rank 95th percentile
+}
+
+impl Stats {
+    pub fn from_values(values: &[f64]) -> Option<Stats> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+        } else {
+            sorted[n / 2]
+        };
+        let variance = if n > 1 {
+            sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        let rank = (0.95 * n as f64).ceil() as usize;
+
+        Some(Stats {
+            count: n,
+            mean,
+            median,
+            stddev: variance.sqrt(),
+            min: sorted[0],
+            max: sorted[n - 1],
+            p95: sorted[rank.max(1) - 1],
+        })
+    }
+}
+
+// Statistics of every stage for one circuit instance
+pub struct Summary {
+    pub proof_type: String,
+    pub num_constraints: usize,
+    pub stages: Vec<(&'static str, Stats)>,
+}
+
+// Groups samples by circuit and size (sweeps share a proof type) in order of
+// first appearance, and summarizes each stage
+pub fn summarize(samples: &[Sample]) -> Vec<Summary> {
+    let mut groups: Vec<(&str, usize, Vec<&Sample>)> = vec![];
+    for sample in samples {
+        let key = (sample.proof_type.as_str(), sample.metrics.num_constraints);
+        match groups.iter_mut().find(|(t, c, _)| (*t, *c) == key) {
+            Some((_, _, group)) => group.push(sample),
+            None => groups.push((key.0, key.1, vec![sample])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(proof_type, num_constraints, group)| Summary {
+            proof_type: proof_type.to_string(),
+            num_constraints,
+            stages: STAGES
+                .iter()
+                .map(|(stage, time)| {
+                    let values: Vec<f64> = group.iter().map(|s| time(s)).collect();
+                    (*stage, Stats::from_values(&values).unwrap())
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+pub const SUMMARY_HEADER: &str =
+    "proof_type,num_constraints,stage,count,mean,median,stddev,min,max,p95";
+
+pub fn write_summary(path: &Path, summaries: &[Summary]) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", SUMMARY_HEADER)?;
+
+    for summary in summaries {
+        for (stage, s) in &summary.stages {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{},{},{}",
+                summary.proof_type,
+                summary.num_constraints,
+                stage,
+                s.count,
+                s.mean,
+                s.median,
+                s.stddev,
+                s.min,
+                s.max,
+                s.p95
+            )?;
+        }
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+pub fn print_summary(summaries: &[Summary]) {
+    println!(
+        "{:<18} {:>11} {:<13} {:>5} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "circuit", "constraints", "stage", "n", "mean", "median", "stddev", "min", "max", "p95"
+    );
+    let ms = |seconds: f64| format!("{:.3}ms", seconds * 1000.0);
+
+    for summary in summaries {
+        for (stage, s) in &summary.stages {
+            println!(
+                "{:<18} {:>11} {:<13} {:>5} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+                summary.proof_type,
+                summary.num_constraints,
+                stage,
+                s.count,
+                ms(s.mean),
+                ms(s.median),
+                ms(s.stddev),
+                ms(s.min),
+                ms(s.max),
+                ms(s.p95)
+            );
+        }
+    }
+}
+
+// A single proof of a benchmark round, independent of the others
+pub type Task = Box<dyn Fn(&Harness) -> Result<Sample, Box<dyn Error>> + Send + Sync>;
+
+// The proofs of one benchmark round of a circuit. Fibonacci and the univariate
+// polynomial sweep their size, to see how prover time scales.
+pub fn round_tasks(circuit: &str) -> Result<Vec<Task>, Box<dyn Error>> {
+    let tasks: Vec<Task> = match circuit {
+        "polynomial" => vec![Box::new(verify_polynomial)],
+        "univariate_poly" => [1, 10, 100, 1000]
+            .into_iter()
+            .map(|degree| -> Task {
+                let coefficients: Vec<u64> = (1..=degree + 1).collect();
+                Box::new(move |h| verify_univariate_polynomial(h, &coefficients, 3, false))
+            })
+            .collect(),
+        "mat_mul" => vec![Box::new(|h| {
+            verify_matrix_multiplication(
+                h,
+                &[vec![123, 456], vec![789, 101]],
+                &[vec![112, 131], vec![415, 161]],
+            )
+        })],
+        "multivar_poly" => vec![Box::new(verify_multivar_polynomial)],
+        "division" => vec![Box::new(verify_division)],
+        "integer_division" => vec![Box::new(verify_integer_division)],
+        "xor" => vec![Box::new(verify_xor)],
+        "fibonacci" => [10, 15, 20, 25]
+            .into_iter()
+            .map(|n| -> Task { Box::new(move |h| verify_fibonacci(h, n)) })
+            .collect(),
+        "sudoku" => vec![Box::new(verify_sudoku)],
+        // One, two and three compression blocks once padded
+        "sha256" => [32, 64, 128]
+            .into_iter()
+            .map(|len| -> Task { Box::new(move |h| verify_sha256(h, len)) })
+            .collect(),
+        // Enough elements to hold the SHA-256 preimages above, at 31 bytes each
+        "poseidon" => [2, 3, 5]
+            .into_iter()
+            .map(|len| -> Task { Box::new(move |h| verify_poseidon(h, len)) })
+            .collect(),
+        "merkle" => [4, 8, 16, 32]
+            .into_iter()
+            .map(|depth| -> Task { Box::new(move |h| verify_merkle(h, depth)) })
+            .collect(),
+        "range" => [8, 32, 64, 128]
+            .into_iter()
+            .map(|num_bits| -> Task { Box::new(move |h| verify_range(h, num_bits)) })
+            .collect(),
+        "mlp" => [4, 8, 16, 32]
+            .into_iter()
+            .map(|width| -> Task { Box::new(move |h| verify_mlp(h, width)) })
+            .collect(),
+        other => return Err(format!("unknown circuit '{}'", other).into()),
+    };
+    Ok(tasks)
+}
+
+// How the proofs of a round are scheduled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // One proof at a time, the prover using rayon's default pool
+    Sequential,
+    // Up to `jobs` proofs at once, for throughput. bellman refuses to wait on
+    // its multi-exponentiations from inside a rayon pool, so each concurrent
+    // proof gets its own OS thread and they all share rayon's global pool.
+    Parallel { jobs: usize },
+    // One proof at a time with the global pool pinned to `threads`, for
+    // latency at a known thread count
+    Isolated { threads: usize },
+}
+
+impl Mode {
+    pub fn describe(&self) -> String {
+        match self {
+            Mode::Sequential => "sequential".to_string(),
+            Mode::Parallel { jobs } => format!("parallel ({} jobs)", jobs),
+            Mode::Isolated { threads } => format!("isolated ({} threads)", threads),
+        }
+    }
+
+    // Sizes rayon's global pool for isolated mode. That pool can only be
+    // configured once per process, before it is first used.
+    pub fn configure(&self) -> Result<(), Box<dyn Error>> {
+        if let Mode::Isolated { threads } = *self {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()?;
+            if rayon::current_num_threads() != threads {
+                return Err(format!("could not pin rayon to {} threads", threads).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+// Runs the tasks and returns their samples in task order
+fn run_tasks(harness: &Harness, tasks: &[Task], mode: Mode) -> Result<Vec<Sample>, Box<dyn Error>> {
+    let jobs = match mode {
+        Mode::Parallel { jobs } => jobs.clamp(1, tasks.len().max(1)),
+        Mode::Sequential | Mode::Isolated { .. } => 1,
+    };
+    if jobs == 1 {
+        return tasks.iter().map(|task| task(harness)).collect();
+    }
+
+    // Workers pull the next task off a shared counter
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<Sample, String>>>> =
+        Mutex::new((0..tasks.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(task) = tasks.get(i) else { break };
+                let result = task(harness).map_err(|e| e.to_string());
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| Ok(result.expect("every task runs")?))
+        .collect()
+}
+
+// Runs `warmup` unrecorded rounds of every circuit, then `iterations`
+// recorded ones, and returns the recorded samples. Warm-up runs one proof at
+// a time and generates any missing parameters, so setup in the measured
+// rounds is the load time. Without warm-up, the store still generates each
+// missing key once, with concurrent proofs of that shape waiting on it.
+pub fn run_benchmark(
+    harness: &Harness,
+    circuits: &[String],
+    warmup: usize,
+    iterations: usize,
+    mode: Mode,
+) -> Result<Vec<Sample>, Box<dyn Error>> {
+    let tasks = circuits
+        .iter()
+        .map(|circuit| round_tasks(circuit))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    // Rows are written here rather than by the tasks, so concurrent proofs
+    // don't interleave their lines
+    let unrecorded = Harness::unrecorded(harness.store.clone());
+    for _ in 0..warmup {
+        run_tasks(&unrecorded, &tasks, Mode::Sequential)?;
+    }
+
+    let mut samples = vec![];
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let round = run_tasks(&unrecorded, &tasks, mode)?;
+        elapsed += start.elapsed();
+
+        if let Some(csv_path) = &harness.csv_path {
+            for sample in &round {
+                write_to_csv(csv_path, &harness.run.run_id, sample)?;
+            }
+        }
+        samples.extend(round);
+    }
+
+    println!(
+        "{} proofs in {:.3}s, {:.2} proofs/s ({})",
+        samples.len(),
+        elapsed.as_secs_f64(),
+        samples.len() as f64 / elapsed.as_secs_f64(),
+        mode.describe()
+    );
+    Ok(samples)
+}
+
+// Time to check `batch_size` proofs one by one and as one batch, in seconds
+#[derive(Clone, Copy, Debug)]
+pub struct BatchSample {
+    pub batch_size: usize,
+    pub loop_time: f64,
+    pub batch_time: f64,
+}
+
+pub const BATCH_HEADER: &str = "batch_size,loop_time,batch_time,run_id";
+
+// Compares per-proof verification with `BatchVerifier` on Fibonacci (n = 10)
+// proofs, for each batch size. Key preparation is left out of both timings.
+pub fn run_batch_benchmark(
+    store: &ParamStore,
+    sizes: &[usize],
+    iterations: usize,
+) -> Result<Vec<BatchSample>, Box<dyn Error>> {
+    let blank = fibonacci_job(10, 0, 1).blank;
+    let params = store.load_or_generate("fibonacci", "n10", blank)?;
+    let pvk = prepare_verifying_key(&params.vk);
+    let verifier = BatchVerifier::new(&params.vk);
+
+    // Distinct statements, by varying the second term of the sequence
+    let max = sizes.iter().copied().max().unwrap_or(0);
+    let proofs = (0..max as u64)
+        .map(|f1| {
+            let job = fibonacci_job(10, 0, f1);
+            let proof = create_random_proof(job.circuit, &params, &mut thread_rng())?;
+            Ok((proof, job.public_inputs))
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let mut samples = vec![];
+    for _ in 0..iterations {
+        for &batch_size in sizes {
+            let batch: Vec<(&Proof<Bls12>, &[Fr])> = proofs[..batch_size]
+                .iter()
+                .map(|(proof, inputs)| (proof, inputs.as_slice()))
+                .collect();
+
+            let start = Instant::now();
+            for (proof, inputs) in &batch {
+                verify_proof(&pvk, proof, inputs)?;
+            }
+            let loop_time = start.elapsed();
+
+            let start = Instant::now();
+            verifier.verify(&batch, &mut thread_rng())?;
+            let batch_time = start.elapsed();
+
+            samples.push(BatchSample {
+                batch_size,
+                loop_time: loop_time.as_secs_f64(),
+                batch_time: batch_time.as_secs_f64(),
+            });
+        }
+    }
+    Ok(samples)
+}
+
+pub fn write_batch_results(
+    path: &Path,
+    run_id: &str,
+    samples: &[BatchSample],
+) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", BATCH_HEADER)?;
+    for s in samples {
+        writeln!(file, "{},{},{},{}", s.batch_size, s.loop_time, s.batch_time, run_id)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+pub fn print_batch_summary(samples: &[BatchSample]) {
+    println!(
+        "{:>10} {:>5} {:>14} {:>14} {:>14} {:>8}",
+        "batch size", "n", "loop median", "batch median", "batch/proof", "speedup"
+    );
+
+    let mut sizes: Vec<usize> = samples.iter().map(|s| s.batch_size).collect();
+    sizes.sort();
+    sizes.dedup();
+    for size in sizes {
+        let of_size = || samples.iter().filter(move |s| s.batch_size == size);
+        let stats = |time: fn(&BatchSample) -> f64| {
+            Stats::from_values(&of_size().map(time).collect::<Vec<_>>()).unwrap()
+        };
+        let (per_proof, batch) = (stats(|s| s.loop_time), stats(|s| s.batch_time));
+
+        println!(
+            "{:>10} {:>5} {:>12.3}ms {:>12.3}ms {:>12.3}ms {:>7.2}x",
+            size,
+            batch.count,
+            per_proof.median * 1000.0,
+            batch.median * 1000.0,
+            batch.median * 1000.0 / size.max(1) as f64,
+            per_proof.median / batch.median
+        );
+    }
+}
+
+// One aggregate of `num_proofs` proofs. Times are in seconds, `proof_size`
+// is the compressed size of the aggregate in bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct AggregationSample {
+    pub num_proofs: usize,
+    pub aggregate_time: f64,
+    pub verify_time: f64,
+    pub proof_size: usize,
+    pub srs_time: f64, // generation of the SRS, which all sizes share
+}
+
+pub const AGGREGATION_HEADER: &str =
+    "num_proofs,aggregate_time,verify_time,proof_size,srs_time,run_id";
+
+// Aggregates Fibonacci (n = 10) proofs for each size. These aren't single
+// proofs of a circuit, so they get their own file rather than rows in the
+// results CSV.
+pub fn run_aggregation_benchmark(
+    store: &ParamStore,
+    sizes: &[usize],
+    iterations: usize,
+) -> Result<Vec<AggregationSample>, Box<dyn Error>> {
+    let blank = fibonacci_job(10, 0, 1).blank;
+    let params = store.load_or_generate("fibonacci", "n10", blank)?;
+
+    let max = sizes.iter().copied().max().unwrap_or(0);
+    let start = Instant::now();
+    let srs = AggregationSrs::generate(max, &mut thread_rng());
+    let setup_time = start.elapsed();
+    println!("SRS generation time for {} proofs: {:?}", max, setup_time);
+    let vk = srs.verifier_key();
+
+    let proofs = (0..max as u64)
+        .map(|f1| {
+            let job = fibonacci_job(10, 0, f1);
+            let proof = create_random_proof(job.circuit, &params, &mut thread_rng())?;
+            Ok((proof, job.public_inputs))
+        })
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let mut samples = vec![];
+    for _ in 0..iterations {
+        for &size in sizes {
+            let batch: Vec<(&Proof<Bls12>, &[Fr])> = proofs[..size]
+                .iter()
+                .map(|(proof, inputs)| (proof, inputs.as_slice()))
+                .collect();
+            let inputs: Vec<&[Fr]> = batch.iter().map(|(_, inputs)| *inputs).collect();
+
+            let start = Instant::now();
+            let aggregate = aggregate_proofs(&srs, &batch)?;
+            let aggregate_time = start.elapsed();
+
+            let start = Instant::now();
+            verify_aggregate(&vk, &params.vk, &inputs, &aggregate)
+                .map_err(|e| format!("aggregate of {} proofs failed to verify: {}", size, e))?;
+            let verify_time = start.elapsed();
+            println!(
+                "Aggregated {} proofs into {} bytes ({} separately) in {:?}, verified in {:?}",
+                size,
+                aggregate.size(),
+                size * (2 * G1_SIZE + G2_SIZE),
+                aggregate_time,
+                verify_time
+            );
+
+            samples.push(AggregationSample {
+                num_proofs: size,
+                aggregate_time: aggregate_time.as_secs_f64(),
+                verify_time: verify_time.as_secs_f64(),
+                proof_size: aggregate.size(),
+                srs_time: setup_time.as_secs_f64(),
+            });
+        }
+    }
+    Ok(samples)
+}
+
+pub fn write_aggregation_results(
+    path: &Path,
+    run_id: &str,
+    samples: &[AggregationSample],
+) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", AGGREGATION_HEADER)?;
+    for s in samples {
+        writeln!(
+            file,
+            "{},{},{},{},{},{}",
+            s.num_proofs, s.aggregate_time, s.verify_time, s.proof_size, s.srs_time, run_id
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraint_counter::CircuitMetrics;
+
+    #[test]
+    fn stats_of_known_values() {
+        let values: Vec<f64> = (1..=20).map(f64::from).collect();
+        let stats = Stats::from_values(&values).unwrap();
+
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.mean, 10.5);
+        assert_eq!(stats.median, 10.5);
+        assert_eq!((stats.min, stats.max), (1.0, 20.0));
+        assert_eq!(stats.p95, 19.0);
+        assert!((stats.stddev - 35f64.sqrt()).abs() < 1e-12);
+
+        let single = Stats::from_values(&[3.0]).unwrap();
+        assert_eq!((single.median, single.stddev, single.p95), (3.0, 0.0, 3.0));
+        assert!(Stats::from_values(&[]).is_none());
+    }
+
+    #[test]
+    fn samples_are_grouped_by_circuit_and_size() {
+        let sample = |proof_type: &str, num_constraints, proof_time| Sample {
+            proof_type: proof_type.into(),
+            setup_time: 0.0,
+            params_generated: false,
+            vk_prep_time: 0.0,
+            synthesis_time: 0.0,
+            proof_time,
+            verification_time: 0.0,
+            r_proof_size: 0,
+            s_proof_size: 0,
+            params_size: 0,
+            metrics: CircuitMetrics {
+                num_constraints,
+                ..CircuitMetrics::default()
+            },
+        };
+        let samples = [
+            sample("fibonacci", 9, 1.0),
+            sample("fibonacci", 14, 5.0),
+            sample("fibonacci", 9, 3.0),
+        ];
+
+        let summaries = summarize(&samples);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].num_constraints, 9);
+        assert_eq!(summaries[0].stages.len(), STAGES.len());
+
+        let (stage, proof) = summaries[0].stages[3];
+        assert_eq!(stage, "proof");
+        assert_eq!((proof.count, proof.mean), (2, 2.0));
+    }
+
+    #[test]
+    fn parallel_rounds_keep_task_order() {
+        let dir = std::env::temp_dir().join(format!("bench_parallel_{}", std::process::id()));
+        let harness = Harness::unrecorded(ParamStore::new(&dir));
+        let tasks = round_tasks("fibonacci").unwrap();
+
+        let sequential = run_tasks(&harness, &tasks, Mode::Sequential).unwrap();
+        let parallel = run_tasks(&harness, &tasks, Mode::Parallel { jobs: 4 }).unwrap();
+
+        let sizes = |samples: &[Sample]| -> Vec<usize> {
+            samples.iter().map(|s| s.metrics.num_constraints).collect()
+        };
+        assert_eq!(sizes(&parallel), sizes(&sequential));
+        assert!(parallel.iter().all(|s| !s.params_generated));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parallel_proofs_of_a_new_shape_generate_it_once() {
+        let dir = std::env::temp_dir().join(format!("bench_fresh_{}", std::process::id()));
+        let harness = Harness::unrecorded(ParamStore::new(&dir));
+        let tasks: Vec<Task> = (0..4).flat_map(|_| round_tasks("xor").unwrap()).collect();
+
+        let samples = run_tasks(&harness, &tasks, Mode::Parallel { jobs: 4 }).unwrap();
+        assert_eq!(samples.iter().filter(|s| s.params_generated).count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+use super::common::*;
+use bellman::{LinearCombination, Variable};
+
+// A variable constrained to be 0 or 1
+#[derive(Clone, Copy, Debug)]
+pub struct AllocatedBit {
+    variable: Variable,
+    value: Option<bool>,
+}
+
+impl AllocatedBit {
+    // Allocates a private bit and enforces booleanity: (1 - b) * b = 0
+    pub fn alloc<F: PrimeField, CS: ConstraintSystem<F>>(
+        cs: CS,
+        value: Option<bool>,
+    ) -> Result<Self, SynthesisError> {
+        Self::alloc_field(cs, value.map(|b| if b { F::ONE } else { F::ZERO }))
+    }
+
+    // Allocates an arbitrary field element and enforces that it is a bit.
+    // Useful when the witness comes in as a field element, since any value
+    // other than 0 or 1 makes the booleanity constraint unsatisfiable.
+    pub fn alloc_field<F: PrimeField, CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<F>,
+    ) -> Result<Self, SynthesisError> {
+        let variable = cs.alloc(|| "bit", || value.grab())?;
+
+        cs.enforce(
+            || "boolean constraint",
+            |lc| lc + CS::one() - variable,
+            |lc| lc + variable,
+            |lc| lc,
+        );
+
+        Ok(AllocatedBit {
+            variable,
+            value: value.map(|v| v == F::ONE),
+        })
+    }
+
+    // Wraps a variable whose booleanity is already implied by other
+    // constraints, so no booleanity check is added
+    pub(crate) fn from_constrained(variable: Variable, value: Option<bool>) -> Self {
+        AllocatedBit { variable, value }
+    }
+
+    pub fn get_variable(&self) -> Variable {
+        self.variable
+    }
+
+    pub fn get_value(&self) -> Option<bool> {
+        self.value
+    }
+}
+
+// A bit that is either allocated, the negation of an allocated bit, or a
+// constant. Negation and constants cost no constraints. A binary operation on
+// two non-constant operands allocates one result bit and one constraint; the
+// result needs no booleanity check since it follows from the operands.
+#[derive(Clone, Copy, Debug)]
+pub enum Boolean {
+    Is(AllocatedBit),
+    Not(AllocatedBit),
+    Constant(bool),
+}
+
+impl From<AllocatedBit> for Boolean {
+    fn from(bit: AllocatedBit) -> Self {
+        Boolean::Is(bit)
+    }
+}
+
+impl Boolean {
+    pub fn get_value(&self) -> Option<bool> {
+        match self {
+            Boolean::Is(bit) => bit.get_value(),
+            Boolean::Not(bit) => bit.get_value().map(|b| !b),
+            Boolean::Constant(b) => Some(*b),
+        }
+    }
+
+    // The bit as a linear combination of `one`
+    pub fn lc<F: PrimeField>(&self, one: Variable, coeff: F) -> LinearCombination<F> {
+        match self {
+            Boolean::Is(bit) => LinearCombination::zero() + (coeff, bit.get_variable()),
+            Boolean::Not(bit) => {
+                LinearCombination::zero() + (coeff, one) - (coeff, bit.get_variable())
+            }
+            Boolean::Constant(true) => LinearCombination::zero() + (coeff, one),
+            Boolean::Constant(false) => LinearCombination::zero(),
+        }
+    }
+
+    pub fn not(&self) -> Self {
+        match self {
+            Boolean::Is(bit) => Boolean::Not(*bit),
+            Boolean::Not(bit) => Boolean::Is(*bit),
+            Boolean::Constant(b) => Boolean::Constant(!b),
+        }
+    }
+
+    // Allocates the result bit and lets `enforce` tie it to the operands
+    fn binary_op<F, CS, V, E>(
+        mut cs: CS,
+        value: V,
+        enforce: E,
+    ) -> Result<Self, SynthesisError>
+    where
+        F: PrimeField,
+        CS: ConstraintSystem<F>,
+        V: FnOnce() -> Option<bool>,
+        E: FnOnce(&mut CS, Variable),
+    {
+        let value = value();
+        let result = cs.alloc(
+            || "result",
+            || {
+                value
+                    .map(|b| if b { F::ONE } else { F::ZERO })
+                    .grab()
+            },
+        )?;
+        enforce(&mut cs, result);
+
+        Ok(Boolean::Is(AllocatedBit::from_constrained(result, value)))
+    }
+
+    // a AND b, enforced as a * b = c
+    pub fn and<F: PrimeField, CS: ConstraintSystem<F>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        match (a, b) {
+            (Boolean::Constant(false), _) | (_, Boolean::Constant(false)) => {
+                Ok(Boolean::Constant(false))
+            }
+            (Boolean::Constant(true), x) | (x, Boolean::Constant(true)) => Ok(*x),
+            _ => Self::binary_op(
+                cs,
+                || Some(a.get_value()? & b.get_value()?),
+                |cs, c| {
+                    cs.enforce(
+                        || "and constraint",
+                        |_| a.lc(CS::one(), F::ONE),
+                        |_| b.lc(CS::one(), F::ONE),
+                        |lc| lc + c,
+                    )
+                },
+            ),
+        }
+    }
+
+    // a OR b, enforced as a * b = a + b - c
+    pub fn or<F: PrimeField, CS: ConstraintSystem<F>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        match (a, b) {
+            (Boolean::Constant(true), _) | (_, Boolean::Constant(true)) => {
+                Ok(Boolean::Constant(true))
+            }
+            (Boolean::Constant(false), x) | (x, Boolean::Constant(false)) => Ok(*x),
+            _ => Self::binary_op(
+                cs,
+                || Some(a.get_value()? | b.get_value()?),
+                |cs, c| {
+                    cs.enforce(
+                        || "or constraint",
+                        |_| a.lc(CS::one(), F::ONE),
+                        |_| b.lc(CS::one(), F::ONE),
+                        |_| a.lc(CS::one(), F::ONE) + &b.lc(CS::one(), F::ONE) - c,
+                    )
+                },
+            ),
+        }
+    }
+
+    // a XOR b, enforced as 2a * b = a + b - c
+    pub fn xor<F: PrimeField, CS: ConstraintSystem<F>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        match (a, b) {
+            (Boolean::Constant(false), x) | (x, Boolean::Constant(false)) => Ok(*x),
+            (Boolean::Constant(true), x) | (x, Boolean::Constant(true)) => Ok(x.not()),
+            _ => Self::binary_op(
+                cs,
+                || Some(a.get_value()? ^ b.get_value()?),
+                |cs, c| {
+                    cs.enforce(
+                        || "xor constraint",
+                        |_| a.lc(CS::one(), F::from(2)),
+                        |_| b.lc(CS::one(), F::ONE),
+                        |_| a.lc(CS::one(), F::ONE) + &b.lc(CS::one(), F::ONE) - c,
+                    )
+                },
+            ),
+        }
+    }
+
+    // NOT (a AND b); the negation itself is free
+    pub fn nand<F: PrimeField, CS: ConstraintSystem<F>>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        Ok(Self::and(cs, a, b)?.not())
+    }
+
+    // Enforces that the bit equals `value`, e.g. a public input
+    pub fn enforce_equal<F: PrimeField, CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        value: Variable,
+    ) {
+        cs.enforce(
+            || "equality constraint",
+            |_| a.lc(CS::one(), F::ONE),
+            |lc| lc + CS::one(),
+            |lc| lc + value,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bellman::Namespace;
+    use bls12_381::Scalar as Fr;
+
+    type BinaryOp = fn(
+        Namespace<Fr, TestConstraintSystem<Fr>>,
+        &Boolean,
+        &Boolean,
+    ) -> Result<Boolean, SynthesisError>;
+
+    fn operands(
+        cs: &mut TestConstraintSystem<Fr>,
+        a: bool,
+        b: bool,
+    ) -> Vec<(Boolean, Boolean)> {
+        let x = AllocatedBit::alloc(cs.namespace(|| "a"), Some(a)).unwrap();
+        let not_x = AllocatedBit::alloc(cs.namespace(|| "not a"), Some(!a)).unwrap();
+        let y = AllocatedBit::alloc(cs.namespace(|| "b"), Some(b)).unwrap();
+        let not_y = AllocatedBit::alloc(cs.namespace(|| "not b"), Some(!b)).unwrap();
+
+        // Every mix of allocated, negated and constant operands
+        let xs = [Boolean::Is(x), Boolean::Not(not_x), Boolean::Constant(a)];
+        let ys = [Boolean::Is(y), Boolean::Not(not_y), Boolean::Constant(b)];
+        xs.iter()
+            .flat_map(|x| ys.iter().map(move |y| (*x, *y)))
+            .collect()
+    }
+
+    fn check_truth_table(op: BinaryOp, expected: fn(bool, bool) -> bool) {
+        for a in [false, true] {
+            for b in [false, true] {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                for (i, (x, y)) in operands(&mut cs, a, b).iter().enumerate() {
+                    let result = op(cs.namespace(|| format!("op {}", i)), x, y).unwrap();
+                    assert_eq!(result.get_value(), Some(expected(a, b)), "{} {}", a, b);
+
+                    let mut ns = cs.namespace(|| format!("check {}", i));
+                    let expected_var = ns
+                        .alloc(|| "expected", || Ok(Fr::from(expected(a, b) as u64)))
+                        .unwrap();
+                    Boolean::enforce_equal(ns, &result, expected_var);
+                }
+                assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn and_truth_table() {
+        check_truth_table(|cs, a, b| Boolean::and(cs, a, b), |a, b| a & b);
+    }
+
+    #[test]
+    fn or_truth_table() {
+        check_truth_table(|cs, a, b| Boolean::or(cs, a, b), |a, b| a | b);
+    }
+
+    #[test]
+    fn xor_truth_table() {
+        check_truth_table(|cs, a, b| Boolean::xor(cs, a, b), |a, b| a ^ b);
+    }
+
+    #[test]
+    fn nand_truth_table() {
+        check_truth_table(|cs, a, b| Boolean::nand(cs, a, b), |a, b| !(a & b));
+    }
+
+    #[test]
+    fn not_is_free() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let bit = AllocatedBit::alloc(cs.namespace(|| "a"), Some(true)).unwrap();
+        let not = Boolean::from(bit).not();
+
+        assert_eq!(not.get_value(), Some(false));
+        assert_eq!(not.not().get_value(), Some(true));
+        assert_eq!(cs.num_constraints(), 1);
+    }
+
+    #[test]
+    fn non_boolean_value_is_rejected() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        AllocatedBit::alloc_field(cs.namespace(|| "a"), Some(Fr::from(2))).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("a/boolean constraint"));
+    }
+}
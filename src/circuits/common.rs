@@ -1,5 +1,5 @@
 pub use bellman::{Circuit, ConstraintSystem, SynthesisError,};
-pub use ff::PrimeField;
+pub use ff::{Field, PrimeField, PrimeFieldBits};
 
 pub trait OptionExt<T> {
     fn grab(&self) -> Result<T, SynthesisError>;
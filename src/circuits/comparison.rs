@@ -0,0 +1,220 @@
+use super::boolean::{AllocatedBit, Boolean};
+use super::common::*;
+use super::nonzero::inverse;
+use super::num::Num;
+use super::range::enforce_bits;
+
+fn from_bool<F: PrimeField>(b: bool) -> F {
+    if b {
+        F::ONE
+    } else {
+        F::ZERO
+    }
+}
+
+// Returns a bit that is 1 iff x = 0, using
+//   x * inv = 1 - out
+//   x * out = 0
+// The mirror image of `is_nonzero`, for linear combinations.
+pub fn is_zero<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    x: &Num<F>,
+) -> Result<Boolean, SynthesisError> {
+    let out_value = x.get_value().map(|v| bool::from(v.is_zero()));
+
+    let inv = cs.alloc(|| "inverse", || inverse(x.get_value()).grab())?;
+    let out = cs.alloc(|| "is zero", || out_value.map(from_bool).grab())?;
+
+    cs.enforce(
+        || "inverse constraint",
+        |_| x.lc(),
+        |lc| lc + inv,
+        |lc| lc + CS::one() - out,
+    );
+    cs.enforce(|| "zero constraint", |_| x.lc(), |lc| lc + out, |lc| lc);
+
+    Ok(Boolean::Is(AllocatedBit::from_constrained(out, out_value)))
+}
+
+// 1 iff a = b, 2 constraints
+pub fn is_equal<F: PrimeField, CS: ConstraintSystem<F>>(
+    cs: CS,
+    a: &Num<F>,
+    b: &Num<F>,
+) -> Result<Boolean, SynthesisError> {
+    is_zero(cs, &a.clone().add_scaled(-F::ONE, b))
+}
+
+// Returns a bit that is 1 iff a < b. Both have to be known to lie in
+// [0, 2^num_bits) already, e.g. from `enforce_bits`; the result means nothing
+// otherwise. a - b + 2^num_bits then lies in [1, 2^(num_bits + 1)), and its
+// top bit is set iff a >= b. num_bits + 2 constraints.
+pub fn less_than<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &Num<F>,
+    b: &Num<F>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError> {
+    assert!(num_bits < F::CAPACITY as usize);
+
+    let offset = Num::constant(CS::one(), F::from(2).pow_vartime([num_bits as u64]));
+    let shifted = a.clone().add_scaled(-F::ONE, b).plus(&offset);
+    let bits = enforce_bits(
+        cs.namespace(|| "difference"),
+        &shifted.lc(),
+        shifted.get_value(),
+        num_bits + 1,
+    )?;
+
+    Ok(Boolean::Is(bits[num_bits]).not())
+}
+
+// 1 iff a >= b, under the same assumptions as `less_than`
+pub fn greater_or_equal<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    cs: CS,
+    a: &Num<F>,
+    b: &Num<F>,
+    num_bits: usize,
+) -> Result<Boolean, SynthesisError> {
+    Ok(less_than(cs, a, b, num_bits)?.not())
+}
+
+// if_true if the bit is set, else if_false, enforced as
+//   bit * (if_true - if_false) = out - if_false
+pub fn select<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    bit: &Boolean,
+    if_true: &Num<F>,
+    if_false: &Num<F>,
+) -> Result<Num<F>, SynthesisError> {
+    let value = match bit.get_value() {
+        Some(true) => if_true.get_value(),
+        Some(false) => if_false.get_value(),
+        None => None,
+    };
+    let out = Num::alloc(cs.namespace(|| "out"), value)?;
+
+    cs.enforce(
+        || "select constraint",
+        |_| bit.lc(CS::one(), F::ONE),
+        |_| if_true.lc() - &if_false.lc(),
+        |_| out.lc() - &if_false.lc(),
+    );
+
+    Ok(out)
+}
+
+// The larger of a and b, under the same assumptions as `less_than`
+pub fn max<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &Num<F>,
+    b: &Num<F>,
+    num_bits: usize,
+) -> Result<Num<F>, SynthesisError> {
+    let a_less = less_than(cs.namespace(|| "less than"), a, b, num_bits)?;
+    select(cs.namespace(|| "select"), &a_less, b, a)
+}
+
+// The smaller of a and b, under the same assumptions as `less_than`
+pub fn min<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    a: &Num<F>,
+    b: &Num<F>,
+    num_bits: usize,
+) -> Result<Num<F>, SynthesisError> {
+    let a_less = less_than(cs.namespace(|| "less than"), a, b, num_bits)?;
+    select(cs.namespace(|| "select"), &a_less, a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn nums(cs: &mut TestConstraintSystem<Fr>, values: &[Fr]) -> Vec<Num<Fr>> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Num::alloc(cs.namespace(|| format!("x{}", i)), Some(*v)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn is_zero_and_is_equal_at_boundaries() {
+        for (value, expected) in [(Fr::ZERO, true), (Fr::ONE, false), (-Fr::ONE, false)] {
+            let mut cs = TestConstraintSystem::new();
+            let x = nums(&mut cs, &[value]);
+            let bit = is_zero(cs.namespace(|| "is zero"), &x[0]).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(bit.get_value(), Some(expected));
+            assert_eq!(cs.get("is zero/is zero"), Fr::from(expected as u64));
+            assert_eq!(cs.num_constraints(), 2);
+        }
+
+        for (a, b) in [(5, 5), (5, 6), (0, 0)] {
+            let mut cs = TestConstraintSystem::new();
+            let x = nums(&mut cs, &[Fr::from(a), Fr::from(b)]);
+            let bit = is_equal(cs.namespace(|| "equal"), &x[0], &x[1]).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(bit.get_value(), Some(a == b));
+        }
+
+        // 0 and p - 1 wrap around to each other only modulo p
+        let mut cs = TestConstraintSystem::new();
+        let x = nums(&mut cs, &[Fr::ZERO, -Fr::ONE]);
+        let bit = is_equal(cs.namespace(|| "equal"), &x[0], &x[1]).unwrap();
+        assert_eq!(bit.get_value(), Some(false));
+    }
+
+    #[test]
+    fn less_than_agrees_on_every_pair() {
+        // Every pair of 3-bit values, including both ends of the range
+        for a in 0..8u64 {
+            for b in 0..8u64 {
+                let mut cs = TestConstraintSystem::new();
+                let x = nums(&mut cs, &[Fr::from(a), Fr::from(b)]);
+                let lt = less_than(cs.namespace(|| "lt"), &x[0], &x[1], 3).unwrap();
+                let ge = greater_or_equal(cs.namespace(|| "ge"), &x[0], &x[1], 3).unwrap();
+
+                assert!(cs.is_satisfied(), "{} {}", a, b);
+                assert_eq!(lt.get_value(), Some(a < b), "{} {}", a, b);
+                assert_eq!(ge.get_value(), Some(a >= b), "{} {}", a, b);
+                assert_eq!(cs.num_constraints(), 2 * 5);
+            }
+        }
+    }
+
+    #[test]
+    fn less_than_at_the_edges_of_wide_ranges() {
+        let top = (1u128 << 64) - 1;
+        for (a, b) in [(0, top), (top, 0), (top, top), (top - 1, top), (top, top - 1)] {
+            let mut cs = TestConstraintSystem::new();
+            let x = nums(&mut cs, &[Fr::from_u128(a), Fr::from_u128(b)]);
+            let lt = less_than(cs.namespace(|| "lt"), &x[0], &x[1], 64).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(lt.get_value(), Some(a < b), "{} {}", a, b);
+        }
+
+        // An operand outside the assumed range leaves the difference too wide
+        let mut cs = TestConstraintSystem::new();
+        let x = nums(&mut cs, &[Fr::from(256), Fr::ZERO]);
+        less_than(cs.namespace(|| "lt"), &x[0], &x[1], 8).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("lt/difference/packing constraint"));
+    }
+
+    #[test]
+    fn max_and_min_pick_the_right_operand() {
+        for (a, b) in [(3, 9), (9, 3), (7, 7), (0, 255), (255, 0)] {
+            let mut cs = TestConstraintSystem::new();
+            let x = nums(&mut cs, &[Fr::from(a), Fr::from(b)]);
+            let larger = max(cs.namespace(|| "max"), &x[0], &x[1], 8).unwrap();
+            let smaller = min(cs.namespace(|| "min"), &x[0], &x[1], 8).unwrap();
+
+            assert!(cs.is_satisfied(), "{} {}", a, b);
+            assert_eq!(larger.get_value(), Some(Fr::from(a.max(b))));
+            assert_eq!(smaller.get_value(), Some(Fr::from(a.min(b))));
+            assert_eq!(cs.get("max/select/out/num"), Fr::from(a.max(b)));
+        }
+    }
+}
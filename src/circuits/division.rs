@@ -1,5 +1,9 @@
 use super::common::*;
+use super::nonzero::assert_nonzero;
+use super::range::enforce_bits;
+use bellman::LinearCombination;
 
+#[derive(Clone)]
 pub struct DivisionCircuit<F: PrimeField> {
     pub numerator: Option<F>,
     pub denominator: Option<F>,
@@ -13,22 +17,191 @@ impl<F: PrimeField> Circuit<F> for DivisionCircuit<F> {
         let denominator = cs.alloc(|| "denominator", || self.denominator.ok_or(SynthesisError::AssignmentMissing))?;
         let quotient = cs.alloc_input(|| "quotient", || self.quotient.ok_or(SynthesisError::AssignmentMissing))?;
         
-        // Ensure denominator is non-zero (optional, but adds robustness)
+        // Ensure denominator is non-zero, otherwise 0 / 0 proves any quotient
+        assert_nonzero(cs.namespace(|| "non-zero denominator"), denominator, self.denominator)?;
+
+        // Enforce division constraint: numerator = quotient * denominator
         cs.enforce(
-            || "non-zero denominator",
-            |lc| lc + denominator,
-            |lc| lc + CS::one(),
+            || "division constraint",
+            |lc| lc + quotient,
             |lc| lc + denominator,
+            |lc| lc + numerator,
         );
 
-        // Enforce division constraint: numerator = quotient * denominator
+        Ok(())
+    }
+}
+
+// Circuit: integer division with remainder, numerator = quotient * denominator + remainder
+// with remainder < denominator. Quotient, denominator and remainder are range
+// checked to `num_bits` bits so the relation can't wrap around the field modulus.
+#[derive(Clone)]
+pub struct IntegerDivisionCircuit<F: PrimeField> {
+    pub numerator: Option<F>,
+    pub denominator: Option<F>,
+    pub quotient: Option<F>,
+    pub remainder: Option<F>,
+    pub num_bits: usize,
+}
+
+impl<F: PrimeFieldBits> Circuit<F> for IntegerDivisionCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // q * d + r < 2^(2k + 1) must stay below the modulus
+        assert!(2 * self.num_bits < F::CAPACITY as usize);
+
+        let numerator = cs.alloc(|| "numerator", || self.numerator.grab())?;
+        let denominator = cs.alloc(|| "denominator", || self.denominator.grab())?;
+        let remainder = cs.alloc(|| "remainder", || self.remainder.grab())?;
+        let quotient = cs.alloc_input(|| "quotient", || self.quotient.grab())?;
+
+        enforce_bits(
+            cs.namespace(|| "quotient range"),
+            &(LinearCombination::zero() + quotient),
+            self.quotient,
+            self.num_bits,
+        )?;
+        enforce_bits(
+            cs.namespace(|| "denominator range"),
+            &(LinearCombination::zero() + denominator),
+            self.denominator,
+            self.num_bits,
+        )?;
+        enforce_bits(
+            cs.namespace(|| "remainder range"),
+            &(LinearCombination::zero() + remainder),
+            self.remainder,
+            self.num_bits,
+        )?;
+
+        // remainder < denominator  <=>  denominator - remainder - 1 >= 0,
+        // which also rules out a zero denominator
+        let gap = match (self.denominator, self.remainder) {
+            (Some(d), Some(r)) => Some(d - r - F::ONE),
+            _ => None,
+        };
+        enforce_bits(
+            cs.namespace(|| "remainder less than denominator"),
+            &(LinearCombination::zero() + denominator - remainder - CS::one()),
+            gap,
+            self.num_bits,
+        )?;
+
+        // Enforce division constraint: quotient * denominator = numerator - remainder
         cs.enforce(
             || "division constraint",
             |lc| lc + quotient,
             |lc| lc + denominator,
-            |lc| lc + numerator,
+            |lc| lc + numerator - remainder,
         );
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(numerator: u64, denominator: u64, quotient: u64) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        DivisionCircuit {
+            numerator: Some(Fr::from(numerator)),
+            denominator: Some(Fr::from(denominator)),
+            quotient: Some(Fr::from(quotient)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        let cs = synthesize(4003859412, 45678, 87654);
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[Fr::from(87654)]));
+    }
+
+    #[test]
+    fn wrong_quotient_is_rejected() {
+        let cs = synthesize(4003859412, 45678, 87655);
+        assert_eq!(cs.which_is_unsatisfied(), Some("division constraint"));
+    }
+
+    #[test]
+    fn zero_denominator_is_rejected() {
+        // 0 = q * 0 holds for every q, so only the non-zero check catches it
+        let cs = synthesize(0, 0, 12345);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("non-zero denominator/non-zero constraint")
+        );
+    }
+
+    fn synthesize_integer(
+        numerator: u64,
+        denominator: u64,
+        quotient: u64,
+        remainder: u64,
+    ) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        IntegerDivisionCircuit {
+            numerator: Some(Fr::from(numerator)),
+            denominator: Some(Fr::from(denominator)),
+            quotient: Some(Fr::from(quotient)),
+            remainder: Some(Fr::from(remainder)),
+            num_bits: 32,
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn integer_division_is_satisfied() {
+        // 4003859500 = 87654 * 45678 + 88
+        let cs = synthesize_integer(4003859500, 45678, 87654, 88);
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[Fr::from(87654)]));
+    }
+
+    #[test]
+    fn remainder_not_below_denominator_is_rejected() {
+        // 4003859500 = 87653 * 45678 + 45766 holds, but 45766 >= 45678
+        let cs = synthesize_integer(4003859500, 45678, 87653, 45766);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("remainder less than denominator/packing constraint")
+        );
+    }
+
+    #[test]
+    fn integer_division_by_zero_is_rejected() {
+        let cs = synthesize_integer(7, 0, 0, 7);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("remainder less than denominator/packing constraint")
+        );
+    }
+
+    #[test]
+    fn field_quotient_is_rejected() {
+        // 7 / 2 in the field satisfies q * d = n, but q isn't a 32-bit integer
+        let q = Fr::from(7) * Fr::from(2).invert().unwrap();
+        let mut cs = TestConstraintSystem::new();
+        IntegerDivisionCircuit {
+            numerator: Some(Fr::from(7)),
+            denominator: Some(Fr::from(2)),
+            quotient: Some(q),
+            remainder: Some(Fr::ZERO),
+            num_bits: 32,
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("quotient range/packing constraint")
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use super::common::*;
 
 // Circuit: Fibonacci sequence
+#[derive(Clone)]
 pub struct FibonacciCircuit<F: PrimeField> {
     pub f0: Option<F>,        // First term
     pub f1: Option<F>,        // Second term
@@ -57,4 +58,46 @@ impl<F: PrimeField> Circuit<F> for FibonacciCircuit<F> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(f1: u64, fn_val: u64, n: usize) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        FibonacciCircuit {
+            f0: Some(Fr::from(0)),
+            f1: Some(Fr::from(f1)),
+            fn_val: Some(Fr::from(fn_val)),
+            n,
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        for (n, expected) in [(10, 55), (15, 610), (20, 6765), (25, 75025)] {
+            let cs = synthesize(1, expected, n);
+            assert!(cs.is_satisfied(), "F({}) = {}", n, expected);
+            assert_eq!(cs.num_constraints(), n);
+        }
+    }
+
+    #[test]
+    fn wrong_term_is_rejected() {
+        let cs = synthesize(1, 56, 10);
+        assert_eq!(cs.which_is_unsatisfied(), Some("final result constraint"));
+    }
+
+    #[test]
+    fn wrong_seed_is_rejected() {
+        // Starting from (0, 2) doubles every term, so F(10) becomes 110
+        let cs = synthesize(2, 55, 10);
+        assert_eq!(cs.which_is_unsatisfied(), Some("final result constraint"));
+    }
+}
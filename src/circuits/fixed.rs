@@ -0,0 +1,681 @@
+use super::boolean::{AllocatedBit, Boolean};
+use super::common::*;
+use super::comparison::{less_than, select};
+use super::num::Num;
+use super::range::enforce_bits;
+
+// Signed fixed-point numbers: a raw integer x stands for x / 2^frac_bits and
+// has to fit in total_bits bits of two's complement. At most 64 bits keeps
+// products and shifted dividends within i128, and far from wrapping around
+// the modulus in-circuit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedConfig {
+    total_bits: usize,
+    frac_bits: usize,
+}
+
+impl FixedConfig {
+    pub fn new(total_bits: usize, frac_bits: usize) -> Self {
+        assert!(total_bits <= 64, "at most 64 bits");
+        assert!(frac_bits < total_bits, "no room for the sign");
+        FixedConfig {
+            total_bits,
+            frac_bits,
+        }
+    }
+
+    pub fn total_bits(&self) -> usize {
+        self.total_bits
+    }
+
+    pub fn frac_bits(&self) -> usize {
+        self.frac_bits
+    }
+
+    // Whether dot products of `len` terms, as `Fixed::dot` and
+    // `AllocatedFixed::dot` compute them, fit: the products are below
+    // 2^(2 total_bits - 2) in magnitude, so sums of `len` of them and the bias
+    // need to stay clear of i128 and the modulus
+    pub fn fits_dot(&self, len: usize) -> bool {
+        let len_bits = (usize::BITS - (len + 1).leading_zeros()) as usize;
+        2 * self.total_bits + len_bits < 127
+    }
+
+    fn contains(&self, raw: i128) -> bool {
+        let half = 1i128 << (self.total_bits - 1);
+        (-half..half).contains(&raw)
+    }
+}
+
+// The raw arithmetic, shared by the native type and the witnesses of the
+// gadgets so the two can't drift apart. Products round toward minus infinity,
+// like an arithmetic shift. Quotients are Euclidean instead, which rounds down
+// for a positive divisor but up for a negative one: 7 / -2 = -3.
+fn mul_raw(a: i128, b: i128, frac_bits: usize) -> i128 {
+    (a * b) >> frac_bits
+}
+
+// Euclidean, so the remainder is never negative; None for a zero divisor
+fn div_raw(a: i128, b: i128, frac_bits: usize) -> Option<(i128, i128)> {
+    let dividend = a << frac_bits;
+    (b != 0).then(|| (dividend.div_euclid(b), dividend.rem_euclid(b)))
+}
+
+fn truncate_raw(a: i128, frac_bits: usize) -> i128 {
+    a >> frac_bits << frac_bits
+}
+
+// The sum of the products and the bias at the products' scale, before the
+// single rescale of `dot`
+fn dot_sum(a: &[i128], b: &[i128], bias: i128, frac_bits: usize) -> i128 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum::<i128>() + (bias << frac_bits)
+}
+
+fn assert_dot_fits(config: FixedConfig, len: usize) {
+    assert!(config.fits_dot(len), "dot product too long");
+}
+
+fn to_field<F: PrimeField>(raw: i128) -> F {
+    let magnitude = F::from_u128(raw.unsigned_abs());
+    if raw < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+// Native fixed-point value, to compute what the gadgets should produce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fixed {
+    raw: i128,
+    config: FixedConfig,
+}
+
+impl Fixed {
+    // None if `raw` doesn't fit
+    pub fn from_raw(config: FixedConfig, raw: i128) -> Option<Self> {
+        config.contains(raw).then_some(Fixed { raw, config })
+    }
+
+    // Rounds to the nearest representable value; None if it doesn't fit
+    pub fn from_f64(config: FixedConfig, x: f64) -> Option<Self> {
+        let scaled = (x * (1u64 << config.frac_bits) as f64).round();
+        if !scaled.is_finite() || scaled.abs() > i64::MAX as f64 {
+            return None;
+        }
+        Self::from_raw(config, scaled as i128)
+    }
+
+    pub fn raw(&self) -> i128 {
+        self.raw
+    }
+
+    pub fn config(&self) -> FixedConfig {
+        self.config
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.raw as f64 / (1u64 << self.config.frac_bits) as f64
+    }
+
+    pub fn to_field<F: PrimeField>(&self) -> F {
+        to_field(self.raw)
+    }
+
+    fn with_raw(&self, raw: i128) -> Option<Self> {
+        Self::from_raw(self.config, raw)
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        assert_eq!(self.config, other.config);
+        self.with_raw(self.raw + other.raw)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        assert_eq!(self.config, other.config);
+        self.with_raw(self.raw - other.raw)
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        assert_eq!(self.config, other.config);
+        self.with_raw(mul_raw(self.raw, other.raw, self.config.frac_bits))
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        assert_eq!(self.config, other.config);
+        let (quotient, _) = div_raw(self.raw, other.raw, self.config.frac_bits)?;
+        self.with_raw(quotient)
+    }
+
+    // Drops the fractional bits, i.e. the floor
+    pub fn truncate(self) -> Self {
+        Fixed {
+            raw: truncate_raw(self.raw, self.config.frac_bits),
+            config: self.config,
+        }
+    }
+
+    // sum a_i * b_i + bias, rounded down once at the end
+    pub fn dot(a: &[Self], b: &[Self], bias: Self) -> Option<Self> {
+        assert_eq!(a.len(), b.len());
+        assert!(a.iter().chain(b).all(|x| x.config == bias.config));
+        assert_dot_fits(bias.config, a.len());
+
+        let raw = |xs: &[Self]| xs.iter().map(|x| x.raw).collect::<Vec<_>>();
+        let sum = dot_sum(&raw(a), &raw(b), bias.raw, bias.config.frac_bits);
+        bias.with_raw(sum >> bias.config.frac_bits)
+    }
+
+    pub fn relu(self) -> Self {
+        Fixed {
+            raw: self.raw.max(0),
+            config: self.config,
+        }
+    }
+}
+
+// Enforces that x is a signed `bits`-bit integer by range checking
+// x + 2^(bits - 1) to `bits` bits, bits + 1 constraints
+fn enforce_signed<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    cs: CS,
+    x: &Num<F>,
+    bits: usize,
+) -> Result<(), SynthesisError> {
+    let offset = Num::constant(CS::one(), F::from(2).pow_vartime([bits as u64 - 1]));
+    let shifted = x.clone().plus(&offset);
+    enforce_bits(cs, &shifted.lc(), shifted.get_value(), bits)?;
+    Ok(())
+}
+
+// Witnesses x = high * 2^shift + low with 0 <= low < 2^shift and high a
+// signed high_bits-bit integer. The caller ties the two to x, after which
+// both are unique.
+fn split<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    raw: Option<i128>,
+    shift: usize,
+    high_bits: usize,
+) -> Result<(Num<F>, Num<F>), SynthesisError> {
+    let high_raw = raw.map(|x| x >> shift);
+    let low_raw = raw.map(|x| x - (x >> shift << shift));
+
+    let high = Num::alloc(cs.namespace(|| "high"), high_raw.map(to_field))?;
+    let low = Num::alloc(cs.namespace(|| "low"), low_raw.map(to_field))?;
+    enforce_signed(cs.namespace(|| "high range"), &high, high_bits)?;
+    enforce_bits(cs.namespace(|| "low range"), &low.lc(), low.get_value(), shift)?;
+
+    Ok((high, low))
+}
+
+// A fixed-point number in-circuit. Every gadget range checks its result, so
+// an overflow leaves the circuit unsatisfied instead of wrapping around.
+#[derive(Clone, Debug)]
+pub struct AllocatedFixed<F: PrimeField> {
+    num: Num<F>,
+    raw: Option<i128>,
+    config: FixedConfig,
+}
+
+impl<F: PrimeFieldBits> AllocatedFixed<F> {
+    fn from_num(num: Num<F>, raw: Option<i128>, config: FixedConfig) -> Self {
+        AllocatedFixed { num, raw, config }
+    }
+
+    // Allocates a private value and range checks it, total_bits + 1
+    // constraints
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        config: FixedConfig,
+        value: Option<Fixed>,
+    ) -> Result<Self, SynthesisError> {
+        let raw = value.map(|v| {
+            assert_eq!(v.config, config);
+            v.raw
+        });
+        let num = Num::alloc(cs.namespace(|| "value"), raw.map(to_field))?;
+        enforce_signed(cs.namespace(|| "range"), &num, config.total_bits)?;
+        Ok(Self::from_num(num, raw, config))
+    }
+
+    // Allocates a public input and range checks it, total_bits + 1
+    // constraints
+    pub fn alloc_input<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        config: FixedConfig,
+        value: Option<Fixed>,
+    ) -> Result<Self, SynthesisError> {
+        let raw = value.map(|v| {
+            assert_eq!(v.config, config);
+            v.raw
+        });
+        let input = cs.alloc_input(|| "value", || raw.map(to_field).grab())?;
+        let num = Num::from_variable(input, raw.map(to_field));
+        enforce_signed(cs.namespace(|| "range"), &num, config.total_bits)?;
+        Ok(Self::from_num(num, raw, config))
+    }
+
+    // Exposes the value as a public input, one constraint
+    pub fn inputize<CS: ConstraintSystem<F>>(&self, mut cs: CS) -> Result<(), SynthesisError> {
+        let input = cs.alloc_input(|| "input", || self.num.get_value().grab())?;
+        cs.enforce(
+            || "input constraint",
+            |_| self.num.lc(),
+            |lc| lc + CS::one(),
+            |lc| lc + input,
+        );
+        Ok(())
+    }
+
+    // A constant, no constraints
+    pub fn constant<CS: ConstraintSystem<F>>(value: Fixed) -> Self {
+        let num = Num::constant(CS::one(), value.to_field());
+        Self::from_num(num, Some(value.raw), value.config)
+    }
+
+    pub fn num(&self) -> &Num<F> {
+        &self.num
+    }
+
+    pub fn config(&self) -> FixedConfig {
+        self.config
+    }
+
+    // None without a witness, or if the value overflowed
+    pub fn get_value(&self) -> Option<Fixed> {
+        Fixed::from_raw(self.config, self.raw?)
+    }
+
+    fn same_config(a: &Self, b: &Self) -> FixedConfig {
+        assert_eq!(a.config, b.config, "mixed fixed-point configurations");
+        a.config
+    }
+
+    // a + b, range checked
+    pub fn add<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let config = Self::same_config(a, b);
+        let sum = a.num.clone().plus(&b.num);
+        enforce_signed(cs.namespace(|| "range"), &sum, config.total_bits)?;
+        Ok(Self::from_num(sum, a.raw.zip(b.raw).map(|(a, b)| a + b), config))
+    }
+
+    // a - b, range checked
+    pub fn sub<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let config = Self::same_config(a, b);
+        let difference = a.num.clone().add_scaled(-F::ONE, &b.num);
+        enforce_signed(cs.namespace(|| "range"), &difference, config.total_bits)?;
+        Ok(Self::from_num(difference, a.raw.zip(b.raw).map(|(a, b)| a - b), config))
+    }
+
+    // a * b rescaled by 2^-frac_bits, rounding down:
+    //   a * b = q * 2^frac_bits + r, 0 <= r < 2^frac_bits
+    // with q range checked, total_bits + frac_bits + 3 constraints
+    pub fn mul<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let config = Self::same_config(a, b);
+        let product = a.raw.zip(b.raw).map(|(a, b)| a * b);
+        let (q, r) = split(
+            cs.namespace(|| "rescale"),
+            product,
+            config.frac_bits,
+            config.total_bits,
+        )?;
+
+        let scale = F::from(2).pow_vartime([config.frac_bits as u64]);
+        cs.enforce(
+            || "product constraint",
+            |_| a.num.lc(),
+            |_| b.num.lc(),
+            |_| Num::zero().add_scaled(scale, &q).plus(&r).lc(),
+        );
+
+        let raw = a.raw.zip(b.raw).map(|(a, b)| mul_raw(a, b, config.frac_bits));
+        Ok(Self::from_num(q, raw, config))
+    }
+
+    // a / b, Euclidean on the raw values scaled up by 2^frac_bits:
+    //   q * b = a * 2^frac_bits - r, 0 <= r < |b|
+    // |b| is picked by a witnessed sign bit; the wrong one makes |b| a huge
+    // field element that r can't stay below. A zero divisor can't be proven.
+    // 3 * total_bits + 6 constraints.
+    pub fn div<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+    ) -> Result<Self, SynthesisError> {
+        let config = Self::same_config(a, b);
+        let bits = config.total_bits;
+
+        // A zero divisor gets q = r = 0, which fails the remainder check
+        let (q_raw, r_raw) = match (a.raw, b.raw) {
+            (Some(a), Some(b)) => {
+                let (q, r) = div_raw(a, b, config.frac_bits).unwrap_or((0, 0));
+                (Some(q), Some(r))
+            }
+            _ => (None, None),
+        };
+        let q = Num::alloc(cs.namespace(|| "quotient"), q_raw.map(to_field))?;
+        let r = Num::alloc(cs.namespace(|| "remainder"), r_raw.map(to_field))?;
+        enforce_signed(cs.namespace(|| "quotient range"), &q, bits)?;
+        enforce_bits(cs.namespace(|| "remainder range"), &r.lc(), r.get_value(), bits)?;
+
+        let sign = AllocatedBit::alloc(cs.namespace(|| "sign"), b.raw.map(|b| b >= 0))?;
+        let negated = Num::zero().add_scaled(-F::ONE, &b.num);
+        let magnitude = select(cs.namespace(|| "magnitude"), &Boolean::Is(sign), &b.num, &negated)?;
+        let one = Num::constant(CS::one(), F::ONE);
+        let gap = magnitude.add_scaled(-F::ONE, &r).add_scaled(-F::ONE, &one);
+        enforce_bits(cs.namespace(|| "remainder below divisor"), &gap.lc(), gap.get_value(), bits)?;
+
+        let scale = F::from(2).pow_vartime([config.frac_bits as u64]);
+        cs.enforce(
+            || "quotient constraint",
+            |_| q.lc(),
+            |_| b.num.lc(),
+            |_| Num::zero().add_scaled(scale, &a.num).add_scaled(-F::ONE, &r).lc(),
+        );
+
+        Ok(Self::from_num(q, q_raw, config))
+    }
+
+    // Drops the fractional bits, i.e. the floor:
+    //   a = i * 2^frac_bits + r, 0 <= r < 2^frac_bits
+    // The integer part i is range checked to the integer bits, so it is
+    // unique. total_bits + 3 constraints.
+    pub fn truncate<CS: ConstraintSystem<F>>(mut cs: CS, a: &Self) -> Result<Self, SynthesisError> {
+        let config = a.config;
+        let int_bits = config.total_bits - config.frac_bits;
+        let (i, r) = split(cs.namespace(|| "split"), a.raw, config.frac_bits, int_bits)?;
+
+        let scale = F::from(2).pow_vartime([config.frac_bits as u64]);
+        let truncated = Num::zero().add_scaled(scale, &i);
+        cs.enforce(
+            || "split constraint",
+            |_| truncated.clone().plus(&r).lc(),
+            |lc| lc + CS::one(),
+            |_| a.num.lc(),
+        );
+
+        let raw = a.raw.map(|a| truncate_raw(a, config.frac_bits));
+        Ok(Self::from_num(truncated, raw, config))
+    }
+
+    // sum a_i * b_i + bias, rescaled once at the end instead of per product
+    // and rounding down, like `mul`. len + total_bits + frac_bits + 3
+    // constraints.
+    pub fn dot<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        a: &[Self],
+        b: &[Self],
+        bias: &Self,
+    ) -> Result<Self, SynthesisError> {
+        assert_eq!(a.len(), b.len());
+        let config = bias.config;
+        for x in a.iter().chain(b) {
+            Self::same_config(x, bias);
+        }
+        assert_dot_fits(config, a.len());
+
+        let scale = F::from(2).pow_vartime([config.frac_bits as u64]);
+        let mut sum = Num::zero().add_scaled(scale, &bias.num);
+        for (i, (x, y)) in a.iter().zip(b).enumerate() {
+            let mut cs = cs.namespace(|| format!("term {}", i));
+            let value = x.raw.zip(y.raw).map(|(x, y)| to_field(x * y));
+            let product = Num::alloc(cs.namespace(|| "product"), value)?;
+            cs.enforce(
+                || "product constraint",
+                |_| x.num.lc(),
+                |_| y.num.lc(),
+                |_| product.lc(),
+            );
+            sum = sum.plus(&product);
+        }
+
+        let raws = |xs: &[Self]| xs.iter().map(|x| x.raw).collect::<Option<Vec<_>>>();
+        let sum_raw = match (raws(a), raws(b), bias.raw) {
+            (Some(a), Some(b), Some(bias)) => Some(dot_sum(&a, &b, bias, config.frac_bits)),
+            _ => None,
+        };
+        let (q, r) = split(
+            cs.namespace(|| "rescale"),
+            sum_raw,
+            config.frac_bits,
+            config.total_bits,
+        )?;
+        cs.enforce(
+            || "rescale constraint",
+            |_| Num::zero().add_scaled(scale, &q).plus(&r).lc(),
+            |lc| lc + CS::one(),
+            |_| sum.lc(),
+        );
+
+        Ok(Self::from_num(q, sum_raw.map(|s| s >> config.frac_bits), config))
+    }
+
+    // max(a, 0). The range check every value gets puts a + 2^(total_bits - 1)
+    // in [0, 2^total_bits), so `less_than` against 2^(total_bits - 1) tells
+    // whether a is negative. total_bits + 3 constraints.
+    pub fn relu<CS: ConstraintSystem<F>>(mut cs: CS, a: &Self) -> Result<Self, SynthesisError> {
+        let bits = a.config.total_bits;
+        let offset = Num::constant(CS::one(), F::from(2).pow_vartime([bits as u64 - 1]));
+        let shifted = a.num.clone().plus(&offset);
+
+        let negative = less_than(cs.namespace(|| "sign"), &shifted, &offset, bits)?;
+        let out = select(cs.namespace(|| "select"), &negative, &Num::zero(), &a.num)?;
+
+        Ok(Self::from_num(out, a.raw.map(|a| a.max(0)), a.config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn config() -> FixedConfig {
+        FixedConfig::new(32, 16)
+    }
+
+    fn fixed(x: f64) -> Fixed {
+        Fixed::from_f64(config(), x).unwrap()
+    }
+
+    #[test]
+    fn native_arithmetic() {
+        let c = FixedConfig::new(16, 8);
+        let x = |v| Fixed::from_f64(c, v).unwrap();
+
+        assert_eq!(x(1.5).checked_mul(x(2.25)), Some(x(3.375)));
+        assert_eq!(x(-7.0).checked_div(x(2.0)), Some(x(-3.5)));
+        assert_eq!(x(1.0).checked_div(x(3.0)).unwrap().raw(), 85); // 0.33203125
+        assert_eq!(x(-1.0).checked_div(x(3.0)).unwrap().raw(), -86); // rounds down
+        assert_eq!(x(1.0).checked_div(x(-3.0)).unwrap().raw(), -85); // rounds up
+        assert_eq!(x(-1.0).checked_div(x(-3.0)).unwrap().raw(), 86);
+        let raw = |v: i128| Fixed::from_raw(FixedConfig::new(16, 0), v).unwrap();
+        assert_eq!(raw(7).checked_div(raw(-2)), Some(raw(-3)));
+        assert_eq!(x(-1.25).truncate(), x(-2.0));
+        assert_eq!(x(1.75).truncate(), x(1.0));
+        assert_eq!(x(0.1).raw(), 26);
+
+        // 16 bits with 8 fractional ones cover [-128, 128)
+        assert_eq!(Fixed::from_f64(c, 128.0), None);
+        assert_eq!(x(127.0).checked_add(x(1.0)), None);
+        assert_eq!(x(-128.0).checked_sub(x(0.00390625)), None);
+        assert_eq!(x(16.0).checked_mul(x(8.0)), None);
+        assert_eq!(x(1.0).checked_div(x(0.0)), None);
+    }
+
+    fn alloc(cs: &mut TestConstraintSystem<Fr>, name: &str, x: Fixed) -> AllocatedFixed<Fr> {
+        AllocatedFixed::alloc(cs.namespace(|| name), config(), Some(x)).unwrap()
+    }
+
+    #[test]
+    fn gadgets_match_native_arithmetic() {
+        let max = Fixed::from_raw(config(), (1 << 31) - 1).unwrap();
+        let min = Fixed::from_raw(config(), -(1 << 31)).unwrap();
+        let pairs = [
+            (fixed(3.25), fixed(-1.5)),
+            (fixed(-0.75), fixed(-0.125)),
+            (fixed(1.0 / 3.0), fixed(7.0)),
+            (fixed(1.0), fixed(-3.0)),
+            (fixed(-1.0), fixed(-3.0)),
+            (max, fixed(-1.0)),
+            (min, fixed(1.0)),
+            (fixed(-5.0), fixed(1.0 / 1024.0)),
+        ];
+        for (a, b) in pairs {
+            let mut cs = TestConstraintSystem::new();
+            let (x, y) = (alloc(&mut cs, "a", a), alloc(&mut cs, "b", b));
+
+            let cases = [
+                (AllocatedFixed::add(cs.namespace(|| "add"), &x, &y), a.checked_add(b)),
+                (AllocatedFixed::sub(cs.namespace(|| "sub"), &x, &y), a.checked_sub(b)),
+                (AllocatedFixed::mul(cs.namespace(|| "mul"), &x, &y), a.checked_mul(b)),
+                (AllocatedFixed::div(cs.namespace(|| "div"), &x, &y), a.checked_div(b)),
+                (AllocatedFixed::truncate(cs.namespace(|| "truncate"), &x), Some(a.truncate())),
+            ];
+            let mut overflowed = false;
+            for (result, expected) in cases {
+                let result = result.unwrap();
+                assert_eq!(result.get_value(), expected, "{:?} {:?}", a, b);
+                match expected {
+                    Some(e) => assert_eq!(result.num().get_value(), Some(e.to_field())),
+                    None => overflowed = true,
+                }
+            }
+            assert_eq!(cs.is_satisfied(), !overflowed, "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn constraint_counts() {
+        let mut cs = TestConstraintSystem::new();
+        let (x, y) = (alloc(&mut cs, "a", fixed(2.5)), alloc(&mut cs, "b", fixed(-0.5)));
+        assert_eq!(cs.num_constraints(), 2 * 33);
+
+        AllocatedFixed::add(cs.namespace(|| "add"), &x, &y).unwrap();
+        AllocatedFixed::mul(cs.namespace(|| "mul"), &x, &y).unwrap();
+        AllocatedFixed::div(cs.namespace(|| "div"), &x, &y).unwrap();
+        AllocatedFixed::truncate(cs.namespace(|| "truncate"), &x).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 2 * 33 + 33 + (32 + 16 + 3) + (3 * 32 + 6) + (32 + 3));
+    }
+
+    #[test]
+    fn dot_and_relu_match_native() {
+        let c = FixedConfig::new(16, 8);
+        let x = |v| Fixed::from_f64(c, v).unwrap();
+        let (a, b, bias) = ([x(0.5), x(-1.25), x(3.0)], [x(2.0), x(0.75), x(-0.5)], x(0.125));
+
+        // 1 - 0.9375 - 1.5 + 0.125, exact at 8 fractional bits
+        assert_eq!(Fixed::dot(&a, &b, bias), Some(x(-1.3125)));
+        // Rescaling once keeps the bits a rescale per product would drop
+        let (third, three) = (x(1.0 / 3.0), x(3.0));
+        assert_eq!(Fixed::dot(&[third, third], &[three, three], x(0.0)), Some(x(1.9921875)));
+        assert_eq!(Fixed::dot(&[x(100.0)], &[x(2.0)], x(0.0)), None);
+
+        for (a, b, bias) in [
+            (a.to_vec(), b.to_vec(), bias),
+            (vec![third, third], vec![three, three], x(-0.25)),
+            (vec![x(-127.0)], vec![x(1.0)], x(-1.0)),
+            (vec![], vec![], x(-2.5)),
+        ] {
+            let mut cs = TestConstraintSystem::new();
+            let alloc = |cs: &mut TestConstraintSystem<Fr>, name: &str, xs: &[Fixed]| {
+                xs.iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let mut cs = cs.namespace(|| format!("{} {}", name, i));
+                        AllocatedFixed::alloc(&mut cs, c, Some(*v)).unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let (xs, ys) = (alloc(&mut cs, "a", &a), alloc(&mut cs, "b", &b));
+            let bias = AllocatedFixed::alloc(cs.namespace(|| "bias"), c, Some(bias)).unwrap();
+
+            let before = cs.num_constraints();
+            let sum = AllocatedFixed::dot(cs.namespace(|| "dot"), &xs, &ys, &bias).unwrap();
+            assert_eq!(cs.num_constraints() - before, a.len() + 16 + 8 + 3);
+            let relu = AllocatedFixed::relu(cs.namespace(|| "relu"), &sum).unwrap();
+
+            let expected = Fixed::dot(&a, &b, bias.get_value().unwrap());
+            assert_eq!(sum.get_value(), expected);
+            assert_eq!(cs.is_satisfied(), expected.is_some());
+            if let Some(expected) = expected {
+                assert_eq!(relu.get_value(), Some(expected.relu()));
+                assert_eq!(relu.num().get_value(), Some(expected.relu().to_field()));
+            }
+        }
+    }
+
+    #[test]
+    fn relu_at_the_edges_of_the_range() {
+        let max = Fixed::from_raw(config(), (1 << 31) - 1).unwrap();
+        let min = Fixed::from_raw(config(), -(1 << 31)).unwrap();
+        let ulp = Fixed::from_raw(config(), 1).unwrap();
+        let minus_ulp = Fixed::from_raw(config(), -1).unwrap();
+        for value in [min, fixed(-1.0), minus_ulp] {
+            let mut cs = TestConstraintSystem::new();
+            let x = alloc(&mut cs, "x", value);
+            let y = AllocatedFixed::relu(cs.namespace(|| "relu"), &x).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(y.num().get_value(), Some(Fr::ZERO));
+        }
+        for value in [fixed(0.0), ulp, max] {
+            let mut cs = TestConstraintSystem::new();
+            let x = alloc(&mut cs, "x", value);
+            let y = AllocatedFixed::relu(cs.namespace(|| "relu"), &x).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(y.get_value(), Some(value));
+            assert_eq!(cs.num_constraints(), 33 + 32 + 3);
+        }
+    }
+
+    #[test]
+    fn public_values() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let x = AllocatedFixed::alloc_input(cs.namespace(|| "x"), config(), Some(fixed(-2.5)))
+            .unwrap();
+        let y = AllocatedFixed::mul(cs.namespace(|| "square"), &x, &x).unwrap();
+        y.inputize(cs.namespace(|| "y")).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[fixed(-2.5).to_field(), fixed(6.25).to_field()]));
+        assert!(!cs.verify(&[fixed(2.5).to_field(), fixed(6.25).to_field()]));
+    }
+
+    #[test]
+    fn overflow_and_bad_inputs_are_rejected() {
+        let mut cs = TestConstraintSystem::new();
+        let too_big = Fixed {
+            raw: 1 << 31,
+            config: config(),
+        };
+        alloc(&mut cs, "a", too_big);
+        assert_eq!(cs.which_is_unsatisfied(), Some("a/range/packing constraint"));
+
+        let mut cs = TestConstraintSystem::new();
+        let (x, y) = (alloc(&mut cs, "a", fixed(300.0)), alloc(&mut cs, "b", fixed(200.0)));
+        AllocatedFixed::mul(cs.namespace(|| "mul"), &x, &y).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("mul/rescale/high range/packing constraint"));
+
+        let mut cs = TestConstraintSystem::new();
+        let (x, y) = (alloc(&mut cs, "a", fixed(1.0)), alloc(&mut cs, "b", fixed(0.0)));
+        let q = AllocatedFixed::div(cs.namespace(|| "div"), &x, &y).unwrap();
+        assert_eq!(q.get_value(), Some(fixed(0.0)));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("div/remainder below divisor/packing constraint")
+        );
+    }
+}
@@ -1,94 +1,100 @@
 use super::common::*;
+use bellman::{LinearCombination, Variable};
 
-// A circuit that implements:
-// 1. Polynomial evaluation: ax³ + bx² + cx + d
-// 2. Matrix multiplication (2x2)
+// A circuit that implements matrix multiplication m1 * m2 = matrix_result
+// for m1 of size rows x inner and m2 of size inner x cols
+#[derive(Clone)]
 pub struct MatrixMultiplication<F: PrimeField> {
-    
-    // Matrix 1 (2x2)
-    pub m1: Option<[[F; 2]; 2]>,
-    // Matrix 2 (2x2)
-    pub m2: Option<[[F; 2]; 2]>,
-
-    // Expected outputs
-    pub matrix_result: Option<[[F; 2]; 2]>,
+    // Dimensions, needed to synthesize the circuit without any values
+    pub rows: usize,
+    pub inner: usize,
+    pub cols: usize,
+
+    // Matrix 1 (rows x inner)
+    pub m1: Option<Vec<Vec<F>>>,
+    // Matrix 2 (inner x cols)
+    pub m2: Option<Vec<Vec<F>>>,
+
+    // Expected outputs (rows x cols)
+    pub matrix_result: Option<Vec<Vec<F>>>,
+}
+
+// Entry (i, j) of an optional matrix
+fn entry<F: PrimeField>(
+    m: &Option<Vec<Vec<F>>>,
+    i: usize,
+    j: usize,
+) -> Result<F, SynthesisError> {
+    m.as_ref()
+        .and_then(|m| m.get(i))
+        .and_then(|row| row.get(j))
+        .copied()
+        .grab()
 }
 
 impl<F: PrimeField> Circuit<F> for MatrixMultiplication<F> {
     fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-
-        // Matrix multiplication implementation
-        let mut m1_vars = [[None; 2]; 2];
-        let mut m2_vars = [[None; 2]; 2];
-        let mut result_vars = [[None; 2]; 2];
+        // Allocate a whole matrix, row by row
+        fn alloc_matrix<F: PrimeField, CS: ConstraintSystem<F>>(
+            cs: &mut CS,
+            name: &str,
+            m: &Option<Vec<Vec<F>>>,
+            rows: usize,
+            cols: usize,
+            public: bool,
+        ) -> Result<Vec<Vec<Variable>>, SynthesisError> {
+            (0..rows)
+                .map(|i| {
+                    (0..cols)
+                        .map(|j| {
+                            let annotation = || format!("{}[{}][{}]", name, i, j);
+                            let value = || entry(m, i, j);
+                            if public {
+                                cs.alloc_input(annotation, value)
+                            } else {
+                                cs.alloc(annotation, value)
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        }
 
         // Allocate matrix inputs
-        for i in 0..2 {
-            for j in 0..2 {
-                m1_vars[i][j] = Some(cs.alloc(
-                    || format!("m1[{}][{}]", i, j),
-                    || self.m1.map(|m| m[i][j]).grab(),
-                )?);
-
-                m2_vars[i][j] = Some(cs.alloc(
-                    || format!("m2[{}][{}]", i, j),
-                    || self.m2.map(|m| m[i][j]).grab(),
-                )?);
-
-                result_vars[i][j] = Some(cs.alloc_input(
-                    || format!("result[{}][{}]", i, j),
-                    || self.matrix_result.map(|m| m[i][j]).grab(),
-                )?);
-            }
-        }
+        let m1_vars = alloc_matrix(cs, "m1", &self.m1, self.rows, self.inner, false)?;
+        let m2_vars = alloc_matrix(cs, "m2", &self.m2, self.inner, self.cols, false)?;
+        let result_vars =
+            alloc_matrix(cs, "result", &self.matrix_result, self.rows, self.cols, true)?;
 
         // Matrix multiplication constraints
-        for i in 0..2 {
-            for j in 0..2 {
-                let mut sum = None;
-                for k in 0..2 {
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                // The running sum is a linear combination of the products,
+                // so only the products themselves need constraints
+                let mut sum = LinearCombination::zero();
+                for k in 0..self.inner {
                     let product = cs.alloc(
                         || format!("prod[{}][{}][{}]", i, j, k),
-                        || Ok(self.m1.grab()?[i][k] * self.m2.grab()?[k][j]),
+                        || Ok(entry(&self.m1, i, k)? * entry(&self.m2, k, j)?),
                     )?;
 
                     // Enforce m1[i][k] * m2[k][j] = product
                     cs.enforce(
                         || format!("matrix mult constraint {},{},{}", i, j, k),
-                        |lc| lc + m1_vars[i][k].unwrap(),
-                        |lc| lc + m2_vars[k][j].unwrap(),
+                        |lc| lc + m1_vars[i][k],
+                        |lc| lc + m2_vars[k][j],
                         |lc| lc + product,
                     );
 
-                    if sum.is_none() {
-                        sum = Some(product);
-                    } else {
-                        let new_sum = cs.alloc(
-                            || format!("sum[{}][{}][{}]", i, j, k),
-                            || {
-                                Ok(self.m1.grab()?[i][k] * self.m2.grab()?[k][j]
-                                    + self.m1.grab()?[i][k - 1] * self.m2.grab()?[k - 1][j])
-                            },
-                        )?;
-
-                        // Enforce sum + product = new_sum
-                        cs.enforce(
-                            || format!("sum constraint {},{},{}", i, j, k),
-                            |lc| lc + CS::one(),
-                            |lc| lc + sum.unwrap() + product,
-                            |lc| lc + new_sum,
-                        );
-
-                        sum = Some(new_sum);
-                    }
+                    sum = sum + product;
                 }
 
-                // Final result constraint
+                // Final result constraint: sum_k product[i][j][k] = result[i][j]
                 cs.enforce(
                     || format!("result constraint {},{}", i, j),
                     |lc| lc + CS::one(),
-                    |lc| lc + sum.unwrap(),
-                    |lc| lc + result_vars[i][j].unwrap(),
+                    |_| sum,
+                    |lc| lc + result_vars[i][j],
                 );
             }
         }
@@ -96,3 +102,68 @@ impl<F: PrimeField> Circuit<F> for MatrixMultiplication<F> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn to_fr(m: &[Vec<u64>]) -> Vec<Vec<Fr>> {
+        m.iter().map(|row| row.iter().map(|&x| Fr::from(x)).collect()).collect()
+    }
+
+    fn synthesize(
+        m1: &[Vec<u64>],
+        m2: &[Vec<u64>],
+        result: &[Vec<u64>],
+    ) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        MatrixMultiplication {
+            rows: m1.len(),
+            inner: m2.len(),
+            cols: m2[0].len(),
+            m1: Some(to_fr(m1)),
+            m2: Some(to_fr(m2)),
+            matrix_result: Some(to_fr(result)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        let cs = synthesize(
+            &[vec![1, 2], vec![3, 4]],
+            &[vec![5, 6], vec![7, 8]],
+            &[vec![19, 22], vec![43, 50]],
+        );
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 4);
+        assert_eq!(cs.num_constraints(), 2 * 2 * 2 + 2 * 2);
+    }
+
+    #[test]
+    fn non_square_dimensions_are_satisfied() {
+        // (2 x 3) * (3 x 4)
+        let cs = synthesize(
+            &[vec![1, 2, 3], vec![4, 5, 6]],
+            &[vec![1, 0, 2, 1], vec![0, 1, 1, 2], vec![3, 1, 0, 1]],
+            &[vec![10, 5, 4, 8], vec![22, 11, 13, 20]],
+        );
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), 8);
+        assert_eq!(cs.num_constraints(), 2 * 3 * 4 + 2 * 4);
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let cs = synthesize(
+            &[vec![1, 2], vec![3, 4]],
+            &[vec![5, 6], vec![7, 8]],
+            &[vec![19, 22], vec![44, 50]],
+        );
+        assert_eq!(cs.which_is_unsatisfied(), Some("result constraint 1,0"));
+    }
+}
@@ -0,0 +1,403 @@
+use super::boolean::AllocatedBit;
+use super::common::*;
+use super::num::Num;
+use super::poseidon::Poseidon;
+use super::sha256::sha256;
+use bellman::gadgets::boolean::Boolean;
+use bellman::gadgets::num::AllocatedNum;
+use bellman::gadgets::{multipack, sha256::sha256 as sha256_gadget};
+use bellman::LinearCombination;
+
+// The two-to-one hash of a Merkle tree, natively for building trees and
+// in-circuit for checking paths. Both sides have to agree for a proof to
+// verify.
+pub trait MerkleHash<F: PrimeField> {
+    fn compress(&self, left: F, right: F) -> F;
+
+    fn compress_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        left: &Num<F>,
+        right: &Num<F>,
+    ) -> Result<Num<F>, SynthesisError>;
+}
+
+// Algebraic: one permutation, 243 constraints per node
+impl<F: PrimeField> MerkleHash<F> for Poseidon<F> {
+    fn compress(&self, left: F, right: F) -> F {
+        self.hash(&[left, right])
+    }
+
+    fn compress_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        cs: CS,
+        left: &Num<F>,
+        right: &Num<F>,
+    ) -> Result<Num<F>, SynthesisError> {
+        self.hash_gadget(cs, &[left.clone(), right.clone()])
+    }
+}
+
+// Bit-based: SHA-256 of the 32-byte little-endian encodings of both children,
+// with the first CAPACITY digest bits packed back into an element the way
+// multipack does. The children are decomposed strictly, so each has a single
+// encoding. Two compression blocks per node, around 45k constraints.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hash;
+
+fn to_bytes<F: PrimeFieldBits>(x: F) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (i, bit) in x.to_le_bits().iter().take(256).enumerate() {
+        bytes[i / 8] |= (*bit as u8) << (i % 8);
+    }
+    bytes
+}
+
+impl<F: PrimeFieldBits> MerkleHash<F> for Sha256Hash {
+    fn compress(&self, left: F, right: F) -> F {
+        let digest = sha256(&[to_bytes(left), to_bytes(right)].concat());
+        multipack::compute_multipacking(&multipack::bytes_to_bits(&digest)[..F::CAPACITY as usize])
+            [0]
+    }
+
+    fn compress_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        left: &Num<F>,
+        right: &Num<F>,
+    ) -> Result<Num<F>, SynthesisError> {
+        assert!(F::NUM_BITS <= 256);
+
+        let mut preimage = vec![];
+        for (name, child) in [("left", left), ("right", right)] {
+            let mut cs = cs.namespace(|| name);
+            let num = AllocatedNum::alloc(cs.namespace(|| "num"), || child.get_value().grab())?;
+            cs.enforce(
+                || "num constraint",
+                |_| child.lc(),
+                |lc| lc + CS::one(),
+                |lc| lc + num.get_variable(),
+            );
+
+            // Little-endian bits padded to 32 bytes, each byte big-endian for
+            // the gadget
+            let mut bits = num.to_bits_le_strict(cs.namespace(|| "bits"))?;
+            bits.resize(256, Boolean::constant(false));
+            preimage.extend(bits.chunks(8).flat_map(|byte| byte.iter().rev().cloned()));
+        }
+
+        let digest = sha256_gadget(cs.namespace(|| "sha256"), &preimage)?;
+
+        let mut packed = LinearCombination::zero();
+        let mut value = Some(F::ZERO);
+        let mut coeff = F::ONE;
+        for bit in &digest[..F::CAPACITY as usize] {
+            packed = packed + &bit.lc(CS::one(), coeff);
+            value = match (value, bit.get_value()) {
+                (Some(v), Some(b)) => Some(if b { v + coeff } else { v }),
+                _ => None,
+            };
+            coeff = coeff.double();
+        }
+
+        let output = Num::alloc(cs.namespace(|| "output"), value)?;
+        cs.enforce(
+            || "output constraint",
+            |_| packed,
+            |lc| lc + CS::one(),
+            |_| output.lc(),
+        );
+        Ok(output)
+    }
+}
+
+// How many leaves fit in a tree of `depth`
+pub fn capacity(depth: usize) -> usize {
+    u32::try_from(depth)
+        .ok()
+        .and_then(|d| 1usize.checked_shl(d))
+        .unwrap_or(usize::MAX)
+}
+
+// A Merkle tree of a given depth over some leaves, padded with zero leaves.
+// Only the nodes above the given leaves are stored; any other node is the
+// root of an empty subtree, of which there is one per level. That keeps deep
+// trees with few leaves cheap to build.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<F: PrimeField, H> {
+    hasher: H,
+    levels: Vec<Vec<F>>,
+    empty: Vec<F>,
+}
+
+// The siblings of a leaf from the bottom up, and whether the path goes
+// through the right child at each level, i.e. the bits of the leaf's index
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerklePath<F: PrimeField> {
+    pub leaf: F,
+    pub siblings: Vec<F>,
+    pub directions: Vec<bool>,
+}
+
+impl<F: PrimeField, H: MerkleHash<F>> MerkleTree<F, H> {
+    // Panics if the leaves don't fit
+    pub fn new(hasher: H, depth: usize, leaves: &[F]) -> Self {
+        assert!(
+            leaves.len() <= capacity(depth),
+            "{} leaves don't fit in a tree of depth {}",
+            leaves.len(),
+            depth
+        );
+
+        let mut levels = vec![leaves.to_vec()];
+        let mut empty = vec![F::ZERO];
+        for _ in 0..depth {
+            let (nodes, empty_node) = (levels.last().unwrap(), *empty.last().unwrap());
+            let parents = nodes
+                .chunks(2)
+                .map(|pair| hasher.compress(pair[0], pair.get(1).copied().unwrap_or(empty_node)))
+                .collect();
+            levels.push(parents);
+            empty.push(hasher.compress(empty_node, empty_node));
+        }
+
+        MerkleTree {
+            hasher,
+            levels,
+            empty,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    fn node(&self, level: usize, index: usize) -> F {
+        self.levels[level].get(index).copied().unwrap_or(self.empty[level])
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth(), 0)
+    }
+
+    // None unless `index` is one of the given leaves
+    pub fn path(&self, index: usize) -> Option<MerklePath<F>> {
+        let leaf = *self.levels[0].get(index)?;
+        let mut siblings = Vec::with_capacity(self.depth());
+        let mut directions = Vec::with_capacity(self.depth());
+        let mut index = index;
+        for level in 0..self.depth() {
+            siblings.push(self.node(level, index ^ 1));
+            directions.push(index % 2 == 1);
+            index /= 2;
+        }
+
+        Some(MerklePath {
+            leaf,
+            siblings,
+            directions,
+        })
+    }
+}
+
+impl<F: PrimeField> MerklePath<F> {
+    // The root the path leads to
+    pub fn root<H: MerkleHash<F>>(&self, hasher: &H) -> F {
+        self.siblings
+            .iter()
+            .zip(&self.directions)
+            .fold(self.leaf, |node, (&sibling, &right)| {
+                if right {
+                    hasher.compress(sibling, node)
+                } else {
+                    hasher.compress(node, sibling)
+                }
+            })
+    }
+}
+
+// Circuit: membership of a private leaf in the tree with a public root. The
+// siblings and direction bits are private too, and their number is the depth
+// of the tree, which fixes the circuit shape. Besides the hashes, each level
+// costs a booleanity and a swap constraint.
+#[derive(Clone)]
+pub struct MerkleCircuit<F: PrimeField, H> {
+    pub hasher: H,
+    pub leaf: Option<F>,
+    pub siblings: Vec<Option<F>>,
+    pub directions: Vec<Option<bool>>,
+    pub root: Option<F>,
+}
+
+impl<F: PrimeField, H: MerkleHash<F>> MerkleCircuit<F, H> {
+    // The circuit for `path` in a tree with `root`
+    pub fn from_path(hasher: H, path: &MerklePath<F>, root: F) -> Self {
+        MerkleCircuit {
+            hasher,
+            leaf: Some(path.leaf),
+            siblings: path.siblings.iter().copied().map(Some).collect(),
+            directions: path.directions.iter().copied().map(Some).collect(),
+            root: Some(root),
+        }
+    }
+
+    // Blank circuit of the given depth, for parameter generation
+    pub fn blank(hasher: H, depth: usize) -> Self {
+        MerkleCircuit {
+            hasher,
+            leaf: None,
+            siblings: vec![None; depth],
+            directions: vec![None; depth],
+            root: None,
+        }
+    }
+}
+
+impl<F: PrimeField, H: MerkleHash<F>> Circuit<F> for MerkleCircuit<F, H> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        assert_eq!(self.siblings.len(), self.directions.len());
+
+        let mut node = Num::alloc(cs.namespace(|| "leaf"), self.leaf)?;
+        for (level, (sibling, right)) in self.siblings.iter().zip(&self.directions).enumerate() {
+            let mut cs = cs.namespace(|| format!("level {}", level));
+            let sibling = Num::alloc(cs.namespace(|| "sibling"), *sibling)?;
+            let right = AllocatedBit::alloc(cs.namespace(|| "direction"), *right)?;
+
+            // left = node + right * (sibling - node), and right gets the other
+            let left_value = match (node.get_value(), sibling.get_value(), right.get_value()) {
+                (Some(n), Some(s), Some(r)) => Some(if r { s } else { n }),
+                _ => None,
+            };
+            let left = Num::alloc(cs.namespace(|| "left"), left_value)?;
+            cs.enforce(
+                || "swap constraint",
+                |lc| lc + right.get_variable(),
+                |_| sibling.lc() - &node.lc(),
+                |_| left.lc() - &node.lc(),
+            );
+            let right = node.plus(&sibling).add_scaled(-F::ONE, &left);
+
+            node = self.hasher.compress_gadget(cs.namespace(|| "hash"), &left, &right)?;
+        }
+
+        let root = cs.alloc_input(|| "root", || self.root.grab())?;
+        cs.enforce(
+            || "root constraint",
+            |_| node.lc(),
+            |lc| lc + CS::one(),
+            |lc| lc + root,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::poseidon::CONSTRAINTS_PER_PERMUTATION;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn leaves(n: u64) -> Vec<Fr> {
+        (1..=n).map(|i| Fr::from(i * 37)).collect()
+    }
+
+    fn synthesize<H: MerkleHash<Fr>>(circuit: MerkleCircuit<Fr, H>) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        cs
+    }
+
+    #[test]
+    fn sparse_tree_matches_padded_tree() {
+        let poseidon = Poseidon::<Fr>::new();
+        let tree = MerkleTree::new(poseidon.clone(), 3, &leaves(5));
+
+        // Hashing all eight leaves level by level
+        let mut level = leaves(5);
+        level.resize(8, Fr::ZERO);
+        while level.len() > 1 {
+            level = level.chunks(2).map(|p| poseidon.compress(p[0], p[1])).collect();
+        }
+        assert_eq!(tree.root(), level[0]);
+
+        for index in 0..5 {
+            let path = tree.path(index).unwrap();
+            assert_eq!(path.root(&poseidon), tree.root());
+        }
+        assert_eq!(tree.path(5), None);
+
+        // A tree of depth 0 is its leaf
+        assert_eq!(MerkleTree::new(poseidon, 0, &[Fr::from(9)]).root(), Fr::from(9));
+    }
+
+    #[test]
+    #[should_panic(expected = "5 leaves don't fit in a tree of depth 2")]
+    fn too_many_leaves_panic() {
+        MerkleTree::new(Poseidon::<Fr>::new(), 2, &leaves(5));
+    }
+
+    #[test]
+    fn poseidon_paths_are_accepted() {
+        for depth in [1, 3, 6] {
+            let tree = MerkleTree::new(Poseidon::new(), depth, &leaves(6.min(1 << depth)));
+            for index in [0, 1, 6.min(1 << depth) - 1] {
+                let path = tree.path(index).unwrap();
+                let circuit = MerkleCircuit::from_path(Poseidon::new(), &path, tree.root());
+                let cs = synthesize(circuit);
+
+                assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+                assert!(cs.verify(&[tree.root()]));
+                assert_eq!(
+                    cs.num_constraints(),
+                    depth * (CONSTRAINTS_PER_PERMUTATION + 2) + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_paths_are_rejected() {
+        let tree = MerkleTree::new(Poseidon::new(), 4, &leaves(10));
+        let path = tree.path(6).unwrap();
+
+        let mut other_leaf = path.clone();
+        other_leaf.leaf += Fr::ONE;
+        let mut flipped = path.clone();
+        flipped.directions[2] = !flipped.directions[2];
+        let mut sibling = path.clone();
+        sibling.siblings[3] = Fr::ZERO;
+
+        for path in [other_leaf, flipped, sibling] {
+            let cs = synthesize(MerkleCircuit::from_path(Poseidon::new(), &path, tree.root()));
+            assert_eq!(cs.which_is_unsatisfied(), Some("root constraint"));
+        }
+
+        // The right path doesn't verify against another root
+        let cs = synthesize(MerkleCircuit::from_path(Poseidon::new(), &path, tree.root()));
+        assert!(cs.is_satisfied());
+        assert!(!cs.verify(&[tree.root() + Fr::ONE]));
+    }
+
+    #[test]
+    fn sha256_paths_are_accepted() {
+        let tree = MerkleTree::new(Sha256Hash, 2, &leaves(3));
+        for index in [1, 2] {
+            let path = tree.path(index).unwrap();
+            let cs = synthesize(MerkleCircuit::from_path(Sha256Hash, &path, tree.root()));
+            assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+            assert!(cs.verify(&[tree.root()]));
+        }
+
+        // Leaves above 2^254 exercise the strict decomposition
+        let tree = MerkleTree::new(Sha256Hash, 1, &[-Fr::ONE, Fr::ONE]);
+        let path = tree.path(0).unwrap();
+        let cs = synthesize(MerkleCircuit::from_path(Sha256Hash, &path, tree.root()));
+        assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+    }
+}
@@ -0,0 +1,441 @@
+use super::common::*;
+use super::fixed::{AllocatedFixed, Fixed, FixedConfig};
+use super::num::Num;
+use super::poseidon::Poseidon;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// A dense layer: a row of weights per output, and a bias per output
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub weights: Vec<Vec<Fixed>>,
+    pub biases: Vec<Fixed>,
+}
+
+// A multilayer perceptron over fixed-point numbers, with a ReLU after every
+// layer but the last. The salt blinds the commitment to the parameters, so
+// it can't be checked against guessed models; it has to be random and kept
+// as private as the weights.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    config: FixedConfig,
+    salt: u128,
+    layers: Vec<Layer>,
+}
+
+// Decimal values separated by commas or whitespace
+pub fn parse_values(config: FixedConfig, text: &str) -> Result<Vec<Fixed>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let value: f64 = x.parse().map_err(|_| format!("invalid value '{}'", x))?;
+            Fixed::from_f64(config, value).ok_or_else(|| format!("value '{}' out of range", x))
+        })
+        .collect()
+}
+
+impl Mlp {
+    // Checks that every layer takes as many inputs as the one before has
+    // outputs, that its dot products fit `config`, and that all values use it
+    pub fn new(config: FixedConfig, salt: u128, layers: Vec<Layer>) -> Result<Self, String> {
+        if layers.is_empty() {
+            return Err("a model needs at least one layer".into());
+        }
+        let mut width = None;
+        for (l, layer) in layers.iter().enumerate() {
+            let inputs = layer.weights.first().map_or(0, |row| row.len());
+            if layer.weights.is_empty() || inputs == 0 {
+                return Err(format!("layer {} is empty", l + 1));
+            }
+            if layer.biases.len() != layer.weights.len()
+                || layer.weights.iter().any(|row| row.len() != inputs)
+            {
+                return Err(format!("layer {} has rows of different widths", l + 1));
+            }
+            if let Some(width) = width.filter(|&w| w != inputs) {
+                return Err(format!(
+                    "layer {} has {} inputs, the one before {} outputs",
+                    l + 1,
+                    inputs,
+                    width
+                ));
+            }
+            if !config.fits_dot(inputs) {
+                return Err(format!(
+                    "layer {} has {} inputs, too many to sum at {} bits",
+                    l + 1,
+                    inputs,
+                    config.total_bits()
+                ));
+            }
+            width = Some(layer.weights.len());
+        }
+
+        let model = Mlp {
+            config,
+            salt,
+            layers,
+        };
+        if model.parameters().any(|x| x.config() != config) {
+            return Err("mixed fixed-point configurations".into());
+        }
+        Ok(model)
+    }
+
+    // A `salt` line with the salt, a random number below 2^128, then the
+    // layers. A `layer` line starts each layer, followed by a line per output
+    // with its weights, a `|` and its bias. `#` starts a comment, e.g.
+    //
+    //     # 2 inputs, 3 hidden units, 1 output
+    //     salt 280472906617418350436215372151651389947
+    //     layer
+    //     0.5 -1.25 | 0.1
+    //     1 0.75 | 0
+    //     -0.5 2 | -0.25
+    //     layer
+    //     1 -1 0.5 | 0
+    pub fn parse(config: FixedConfig, text: &str) -> Result<Self, String> {
+        let mut layers: Vec<Layer> = vec![];
+        let mut salt = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let at = |e: String| format!("line {}: {}", n + 1, e);
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("salt ") {
+                if salt.is_some() || !layers.is_empty() {
+                    return Err(at("the salt goes once, before the layers".into()));
+                }
+                let value = value.trim();
+                let value = value.parse().map_err(|_| at(format!("invalid salt '{}'", value)))?;
+                salt = Some(value);
+                continue;
+            }
+            if line == "layer" {
+                layers.push(Layer {
+                    weights: vec![],
+                    biases: vec![],
+                });
+                continue;
+            }
+
+            let layer = layers.last_mut().ok_or_else(|| at("expected `layer`".into()))?;
+            let (weights, bias) = line
+                .split_once('|')
+                .ok_or_else(|| at("expected `weights | bias`".into()))?;
+            let bias = match parse_values(config, bias).map_err(at)?[..] {
+                [bias] => bias,
+                _ => return Err(at("expected a single bias".into())),
+            };
+            layer.weights.push(parse_values(config, weights).map_err(at)?);
+            layer.biases.push(bias);
+        }
+
+        let salt = salt.ok_or("a model needs a `salt` line to keep its commitment hiding")?;
+        Mlp::new(config, salt, layers)
+    }
+
+    pub fn read(config: FixedConfig, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Mlp::parse(config, &text).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    pub fn config(&self) -> FixedConfig {
+        self.config
+    }
+
+    pub fn salt(&self) -> u128 {
+        self.salt
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    // The input width, then the output width of each layer
+    pub fn sizes(&self) -> Vec<usize> {
+        let inputs = self.layers[0].weights[0].len();
+        std::iter::once(inputs)
+            .chain(self.layers.iter().map(|layer| layer.weights.len()))
+            .collect()
+    }
+
+    // Weights and biases in the order they are committed to: layer by layer,
+    // each output's weights followed by its bias
+    pub fn parameters(&self) -> impl Iterator<Item = Fixed> + '_ {
+        self.layers.iter().flat_map(|layer| {
+            layer
+                .weights
+                .iter()
+                .zip(&layer.biases)
+                .flat_map(|(row, bias)| row.iter().chain(Some(bias)).copied())
+        })
+    }
+
+    // Poseidon hash of the salt followed by the parameters
+    pub fn commitment<F: PrimeField>(&self) -> F {
+        let parameters = self.parameters().map(|x| x.to_field());
+        let preimage: Vec<F> = std::iter::once(F::from_u128(self.salt)).chain(parameters).collect();
+        Poseidon::new().hash(&preimage)
+    }
+
+    // None if a value overflows
+    pub fn evaluate(&self, input: &[Fixed]) -> Option<Vec<Fixed>> {
+        assert_eq!(input.len(), self.sizes()[0]);
+
+        let mut values = input.to_vec();
+        for (l, layer) in self.layers.iter().enumerate() {
+            values = layer
+                .weights
+                .iter()
+                .zip(&layer.biases)
+                .map(|(row, &bias)| {
+                    let out = Fixed::dot(row, &values, bias)?;
+                    Some(if l + 1 < self.layers.len() { out.relu() } else { out })
+                })
+                .collect::<Option<_>>()?;
+        }
+        Some(values)
+    }
+}
+
+// Circuit: the model committed to produces the output on the input. The
+// input, the output and the Poseidon commitment to the salt and the weights
+// are public, in that order; the salt and the weights are private. `sizes`
+// fixes the circuit shape. Every value is range checked to the configuration,
+// dense layers rescale once per output, and the ReLUs compare against zero
+// with `less_than`.
+#[derive(Clone)]
+pub struct MlpCircuit {
+    pub config: FixedConfig,
+    pub sizes: Vec<usize>,
+    pub model: Option<Mlp>,
+    pub input: Option<Vec<Fixed>>,
+}
+
+impl<F: PrimeFieldBits> Circuit<F> for MlpCircuit {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if let Some(model) = &self.model {
+            assert_eq!(model.sizes(), self.sizes, "model doesn't match the shape");
+            assert_eq!(model.config(), self.config);
+        }
+        let config = self.config;
+
+        let mut values = (0..self.sizes[0])
+            .map(|i| {
+                let value = self.input.as_ref().map(|input| input[i]);
+                AllocatedFixed::alloc_input(cs.namespace(|| format!("input {}", i)), config, value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let layers = self.sizes.len() - 1;
+        let salt = self.model.as_ref().map(|model| F::from_u128(model.salt()));
+        let mut parameters = vec![Num::alloc(cs.namespace(|| "salt"), salt)?];
+        for l in 0..layers {
+            let mut cs = cs.namespace(|| format!("layer {}", l));
+            let layer = self.model.as_ref().map(|model| &model.layers()[l]);
+
+            let mut outputs = Vec::with_capacity(self.sizes[l + 1]);
+            for j in 0..self.sizes[l + 1] {
+                let mut cs = cs.namespace(|| format!("unit {}", j));
+                let weights = (0..self.sizes[l])
+                    .map(|i| {
+                        let value = layer.map(|layer| layer.weights[j][i]);
+                        let cs = cs.namespace(|| format!("weight {}", i));
+                        AllocatedFixed::alloc(cs, config, value)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let bias_value = layer.map(|layer| layer.biases[j]);
+                let bias = AllocatedFixed::alloc(cs.namespace(|| "bias"), config, bias_value)?;
+                parameters.extend(weights.iter().chain(Some(&bias)).map(|x| x.num().clone()));
+
+                let out = AllocatedFixed::dot(cs.namespace(|| "dense"), &weights, &values, &bias)?;
+                outputs.push(if l + 1 < layers {
+                    AllocatedFixed::relu(cs.namespace(|| "relu"), &out)?
+                } else {
+                    out
+                });
+            }
+            values = outputs;
+        }
+
+        for (i, value) in values.iter().enumerate() {
+            value.inputize(cs.namespace(|| format!("output {}", i)))?;
+        }
+
+        let hash = Poseidon::new().hash_gadget(cs.namespace(|| "commitment"), &parameters)?;
+        let commitment = cs.alloc_input(|| "commitment", || hash.get_value().grab())?;
+        cs.enforce(
+            || "commitment constraint",
+            |_| hash.lc(),
+            |lc| lc + CS::one(),
+            |lc| lc + commitment,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::poseidon::CONSTRAINTS_PER_PERMUTATION;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    const MODEL: &str = "
+        # 2 inputs, 3 hidden units, 1 output
+        salt 280472906617418350436215372151651389947
+        layer
+        0.5 -1.25 | 0.125
+        1 0.75 | 0
+        -0.5, 2 | -0.25   # commas work too
+        layer
+        1 -1 0.5 | 0.5
+    ";
+
+    fn config() -> FixedConfig {
+        FixedConfig::new(32, 16)
+    }
+
+    fn values(text: &str) -> Vec<Fixed> {
+        parse_values(config(), text).unwrap()
+    }
+
+    fn synthesize(model: &Mlp, input: &[Fixed]) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        MlpCircuit {
+            config: config(),
+            sizes: model.sizes(),
+            model: Some(model.clone()),
+            input: Some(input.to_vec()),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    fn public_inputs(input: &[Fixed], output: &[Fixed], commitment: Fr) -> Vec<Fr> {
+        let values = input.iter().chain(output).map(|x| x.to_field());
+        values.chain(Some(commitment)).collect()
+    }
+
+    #[test]
+    fn models_are_parsed_and_evaluated() {
+        let model = Mlp::parse(config(), MODEL).unwrap();
+        assert_eq!(model.sizes(), vec![2, 3, 1]);
+        assert_eq!(model.parameters().count(), 13);
+
+        // Hidden: relu(0.5 - 2.5 + 0.125), relu(1 + 1.5), relu(-0.5 + 4 - 0.25)
+        //       = 0, 2.5, 3.25
+        // Output: -2.5 + 1.625 + 0.5
+        assert_eq!(model.evaluate(&values("1, 2")), Some(values("-0.375")));
+    }
+
+    #[test]
+    fn malformed_models_are_rejected() {
+        let parse = |text: &str| Mlp::parse(config(), &format!("salt 7\n{}", text)).unwrap_err();
+        assert_eq!(parse(""), "a model needs at least one layer");
+        assert_eq!(parse("1 2 | 3"), "line 2: expected `layer`");
+        assert_eq!(parse("layer\n1 2"), "line 3: expected `weights | bias`");
+        assert_eq!(parse("layer\n1 2 | 3 4"), "line 3: expected a single bias");
+        assert_eq!(parse("layer\n1 x | 3"), "line 3: invalid value 'x'");
+        assert_eq!(parse("layer\n1 2 | 1e9"), "line 3: value '1e9' out of range");
+        assert_eq!(parse("layer\n1 2 | 0\n1 | 0"), "layer 1 has rows of different widths");
+        assert_eq!(
+            parse("layer\n1 2 | 0\nlayer\n1 2 | 0"),
+            "layer 2 has 2 inputs, the one before 1 outputs"
+        );
+        assert_eq!(parse("layer\nlayer\n1 | 0"), "layer 1 is empty");
+        assert_eq!(parse("layer\n1 | 0\nsalt 8"), "line 4: the salt goes once, before the layers");
+
+        let error = Mlp::parse(config(), "layer\n1 | 0").unwrap_err();
+        assert_eq!(error, "a model needs a `salt` line to keep its commitment hiding");
+        let error = Mlp::parse(config(), "salt -1\nlayer\n1 | 0").unwrap_err();
+        assert_eq!(error, "line 1: invalid salt '-1'");
+    }
+
+    #[test]
+    fn sums_too_wide_for_the_configuration_are_rejected() {
+        // A single product of two 63-bit values already leaves no headroom
+        let wide = FixedConfig::new(63, 16);
+        let error = Mlp::parse(wide, "salt 7\nlayer\n1 | 0").unwrap_err();
+        assert_eq!(error, "layer 1 has 1 inputs, too many to sum at 63 bits");
+
+        // At 60 bits, sums of up to 62 products fit
+        let config = FixedConfig::new(60, 16);
+        let zero = Fixed::from_raw(config, 0).unwrap();
+        let layer = |inputs| Layer {
+            weights: vec![vec![zero; inputs]],
+            biases: vec![zero],
+        };
+        assert!(Mlp::new(config, 7, vec![layer(62)]).is_ok());
+        assert!(Mlp::new(config, 7, vec![layer(63)]).is_err());
+    }
+
+    #[test]
+    fn the_salt_hides_the_weights() {
+        let model = Mlp::parse(config(), MODEL).unwrap();
+        let resalted = Mlp::new(config(), model.salt() + 1, model.layers().to_vec()).unwrap();
+        assert_ne!(model.commitment::<Fr>(), resalted.commitment::<Fr>());
+
+        // A proof for one salt doesn't verify against the other's commitment
+        let input = values("1, 2");
+        let output = model.evaluate(&input).unwrap();
+        let cs = synthesize(&resalted, &input);
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&public_inputs(&input, &output, resalted.commitment())));
+        assert!(!cs.verify(&public_inputs(&input, &output, model.commitment())));
+    }
+
+    #[test]
+    fn inference_is_proven() {
+        let model = Mlp::parse(config(), MODEL).unwrap();
+        for input in [values("1, 2"), values("-3.5, 0.0625"), values("0 0")] {
+            let output = model.evaluate(&input).unwrap();
+            let cs = synthesize(&model, &input);
+
+            assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+            assert!(cs.verify(&public_inputs(&input, &output, model.commitment())));
+
+            // Range checks of the inputs and the 13 parameters, 3 dense units
+            // with 2 inputs and a ReLU each, 1 with 3 inputs, the output and
+            // the commitment to the salt and the parameters
+            let (bits, frac) = (32, 16);
+            let expected = (2 + 13) * (bits + 1)
+                + 3 * ((2 + bits + frac + 3) + (bits + 3))
+                + (3 + bits + frac + 3)
+                + 1
+                + 7 * CONSTRAINTS_PER_PERMUTATION
+                + 1;
+            assert_eq!(cs.num_constraints(), expected);
+        }
+    }
+
+    #[test]
+    fn wrong_outputs_and_models_are_rejected() {
+        let model = Mlp::parse(config(), MODEL).unwrap();
+        let input = values("1, 2");
+        let cs = synthesize(&model, &input);
+
+        assert!(!cs.verify(&public_inputs(&input, &values("-0.25"), model.commitment())));
+        assert!(!cs.verify(&public_inputs(&values("2, 1"), &values("-0.375"), model.commitment())));
+
+        // Another model with the same output on this input
+        let other = Mlp::parse(config(), &MODEL.replace("| 0.125", "| 0.25")).unwrap();
+        assert_eq!(other.evaluate(&input), model.evaluate(&input));
+        assert!(!cs.verify(&public_inputs(&input, &values("-0.375"), other.commitment())));
+
+        // A hidden unit overflowing
+        let big = Mlp::parse(config(), "salt 7\nlayer\n30000 | 0\nlayer\n1 | 0").unwrap();
+        assert_eq!(big.evaluate(&values("2")), None);
+        let cs = synthesize(&big, &values("2"));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("layer 0/unit 0/dense/rescale/high range/packing constraint")
+        );
+    }
+}
@@ -1,7 +1,21 @@
 pub mod common;
+pub mod boolean;
+pub mod nonzero;
+pub mod range;
+pub mod comparison;
+pub mod fixed;
+pub mod num;
+pub mod poseidon;
+pub mod merkle;
 pub mod polynomial;
+pub mod univariate_polynomial;
 pub mod matrix_multiplication;
 pub mod multivar_polynomial;
 pub mod division;
 pub mod xor;
-pub mod fibonacci;
\ No newline at end of file
+pub mod fibonacci;
+pub mod sudoku;
+pub mod sha256;
+pub mod mlp;
+#[cfg(test)]
+pub mod test_cs;
@@ -1,120 +1,344 @@
 use super::common::*;
+use bellman::{LinearCombination, Variable};
+use std::collections::HashMap;
+
+// One term of a polynomial: coefficient * v_0^e_0 * v_1^e_1 * ...
+#[derive(Clone)]
+pub struct Monomial<F: PrimeField> {
+    pub coefficient: Option<F>,
+    pub exponents: Vec<u32>, // one entry per variable
+}
 
 // The circuit implements
-// ax^3y^2 + bx^2y + cxy + d = result
+// sum over terms of coefficient * prod_i v_i^e_i = result
+// with private variables and coefficients and a public result.
+#[derive(Clone)]
 pub struct MultiVarPolynomialCircuit<F: PrimeField> {
-    pub x: Option<F>,
-    pub y: Option<F>,
-    pub a: Option<F>,
-    pub b: Option<F>,
-    pub c: Option<F>,
-    pub d: Option<F>,
+    pub variables: Vec<Option<F>>,
+    pub terms: Vec<Monomial<F>>,
     pub result: Option<F>,
 }
 
+impl<F: PrimeField> MultiVarPolynomialCircuit<F> {
+    // Checks that every term has at most one exponent per variable
+    pub fn new(
+        variables: Vec<Option<F>>,
+        terms: Vec<Monomial<F>>,
+        result: Option<F>,
+    ) -> Result<Self, String> {
+        check_terms(variables.len(), &terms)?;
+        Ok(MultiVarPolynomialCircuit {
+            variables,
+            terms,
+            result,
+        })
+    }
+
+    // ax^3y^2 + bx^2y + cxy + d = result, over the variables (x, y)
+    pub fn ax3y2_bx2y_cxy_d(
+        x: Option<F>,
+        y: Option<F>,
+        a: Option<F>,
+        b: Option<F>,
+        c: Option<F>,
+        d: Option<F>,
+        result: Option<F>,
+    ) -> Self {
+        let term = |coefficient, exponents: [u32; 2]| Monomial {
+            coefficient,
+            exponents: exponents.to_vec(),
+        };
+
+        MultiVarPolynomialCircuit {
+            variables: vec![x, y],
+            terms: vec![
+                term(a, [3, 2]),
+                term(b, [2, 1]),
+                term(c, [1, 1]),
+                term(d, [0, 0]),
+            ],
+            result,
+        }
+    }
+}
+
+fn check_terms<F: PrimeField>(num_variables: usize, terms: &[Monomial<F>]) -> Result<(), String> {
+    match terms
+        .iter()
+        .position(|term| term.exponents.len() > num_variables)
+    {
+        Some(t) => Err(format!(
+            "term {} has {} exponents for {} variables",
+            t,
+            terms[t].exponents.len(),
+            num_variables
+        )),
+        None => Ok(()),
+    }
+}
+
+// Native evaluation, used to compute the expected result
+pub fn evaluate<F: PrimeField>(variables: &[F], terms: &[(F, Vec<u32>)]) -> F {
+    terms
+        .iter()
+        .map(|(coefficient, exponents)| {
+            variables
+                .iter()
+                .zip(exponents)
+                .fold(*coefficient, |acc, (v, e)| acc * v.pow_vartime([*e as u64]))
+        })
+        .sum()
+}
+
+// A variable together with its assignment, if known
+type Allocated<F> = (Variable, Option<F>);
+
+fn mul<F: PrimeField>(a: Option<F>, b: Option<F>) -> Option<F> {
+    Some(a? * b?)
+}
+
 impl<F: PrimeField> Circuit<F> for MultiVarPolynomialCircuit<F> {
     fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // The fields are public, so terms may not have gone through `new`
+        if check_terms(self.variables.len(), &self.terms).is_err() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
         // Allocate input variables
-        let x = cs.alloc(|| "x", || self.x.grab())?;
-        let y = cs.alloc(|| "y", || self.y.grab())?;
-        let a = cs.alloc(|| "a", || self.a.grab())?;
-        let b = cs.alloc(|| "b", || self.b.grab())?;
-        let c = cs.alloc(|| "c", || self.c.grab())?;
-        let d = cs.alloc(|| "d", || self.d.grab())?;
+        let variables = self
+            .variables
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Ok((cs.alloc(|| format!("v{}", i), || v.grab())?, *v)))
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
 
         // Allocate result as an input (since it’s verified externally)
         let result = cs.alloc_input(|| "result", || self.result.grab())?;
 
-        // Step 1: Intermediate terms for x^2, x^3, and y^2
-        let x2 = cs.alloc(|| "x^2", || Ok(self.x.grab()? * self.x.grab()?))?;
-        cs.enforce(|| "x^2 constraint", |lc| lc + x, |lc| lc + x, |lc| lc + x2);
-
-        let x3 = cs.alloc(
-            || "x^3",
-            || Ok(self.x.grab()? * self.x.grab()? * self.x.grab()?),
-        )?;
-        cs.enforce(|| "x^3 constraint", |lc| lc + x2, |lc| lc + x, |lc| lc + x3);
-
-        let y2 = cs.alloc(|| "y^2", || Ok(self.y.grab()? * self.y.grab()?))?;
-        cs.enforce(|| "y^2 constraint", |lc| lc + y, |lc| lc + y, |lc| lc + y2);
-
-        // Step 2: Terms for ax^3y^2, bx^2y, and cxy
-        let ax3 = cs.alloc(
-            || "ax^3",
-            || Ok(self.a.grab()? * self.x.grab()? * self.x.grab()? * self.x.grab()?),
-        )?;
-        cs.enforce(
-            || "ax^3 constraint",
-            |lc| lc + a,
-            |lc| lc + x3,
-            |lc| lc + ax3,
-        );
-
-        let ax3y2 = cs.alloc(
-            || "ax^3y^2",
-            || {
-                Ok(self.a.grab()?
-                    * self.x.grab()?
-                    * self.x.grab()?
-                    * self.x.grab()?
-                    * self.y.grab()?
-                    * self.y.grab()?)
-            },
-        )?;
-
-        cs.enforce(
-            || "ax^3y^2 constraint",
-            |lc| lc + ax3,
-            |lc| lc + y2,
-            |lc| lc + ax3y2,
-        );
-
-        let bx2 = cs.alloc(
-            || "bx^2",
-            || Ok(self.b.grab()? * self.x.grab()? * self.x.grab()?),
-        )?;
-        cs.enforce(
-            || "bx^2 constraint",
-            |lc| lc + b,
-            |lc| lc + x2,
-            |lc| lc + bx2,
-        );
-
-        let bx2y = cs.alloc(
-            || "bx^2y",
-            || Ok(self.b.grab()? * self.x.grab()? * self.x.grab()? * self.y.grab()?),
-        )?;
-
-        cs.enforce(
-            || "bx^2y constraint",
-            |lc| lc + bx2,
-            |lc| lc + y,
-            |lc| lc + bx2y,
-        );
-
-        let cx = cs.alloc(|| "cx", || Ok(self.c.grab()? * self.x.grab()?))?;
-        cs.enforce(|| "cx constraint", |lc| lc + c, |lc| lc + x, |lc| lc + cx);
-
-        let cxy = cs.alloc(
-            || "cxy",
-            || Ok(self.c.grab()? * self.x.grab()? * self.y.grab()?),
-        )?;
-
-        cs.enforce(
-            || "cxy constraint",
-            |lc| lc + cx,
-            |lc| lc + y,
-            |lc| lc + cxy,
-        );
-
-        // Step 3: Enforce the final polynomial constraint: ax^3y^2 + bx^2y + cxy + d = result
-        cs.enforce(
-            || "polynomial constraint",
-            |lc| lc + ax3y2 + bx2y + cxy + d,
-            |lc| lc + CS::one(),
-            |lc| lc + result,
-        );
+        // Powers shared between all terms, keyed by (variable, exponent)
+        let mut powers: HashMap<(usize, u32), Allocated<F>> = HashMap::new();
+
+        fn power<F: PrimeField, CS: ConstraintSystem<F>>(
+            cs: &mut CS,
+            powers: &mut HashMap<(usize, u32), Allocated<F>>,
+            variables: &[Allocated<F>],
+            i: usize,
+            e: u32,
+        ) -> Result<Allocated<F>, SynthesisError> {
+            if e == 1 {
+                return Ok(variables[i]);
+            }
+            if let Some(p) = powers.get(&(i, e)) {
+                return Ok(*p);
+            }
+
+            // Square-and-multiply: x^e = (x^(e/2))^2 or x^(e-1) * x
+            let (a, b) = if e.is_multiple_of(2) {
+                let half = power(cs, powers, variables, i, e / 2)?;
+                (half, half)
+            } else {
+                (power(cs, powers, variables, i, e - 1)?, variables[i])
+            };
+
+            let value = mul(a.1, b.1);
+            let var = cs.alloc(|| format!("v{}^{}", i, e), || value.grab())?;
+            cs.enforce(
+                || format!("v{}^{} constraint", i, e),
+                |lc| lc + a.0,
+                |lc| lc + b.0,
+                |lc| lc + var,
+            );
+
+            powers.insert((i, e), (var, value));
+            Ok((var, value))
+        }
+
+        // Each term is the chain coefficient * p_1 * ... * p_m, one constraint per
+        // factor. The last multiplication of the last non-constant term is
+        // enforced directly against the result, so summing is free.
+        let mut sum = LinearCombination::zero();
+        let mut last: Option<(usize, Allocated<F>, Allocated<F>)> = None;
+
+        for (t, term) in self.terms.iter().enumerate() {
+            let coefficient =
+                cs.alloc(|| format!("coefficient {}", t), || term.coefficient.grab())?;
+
+            let mut factors = vec![];
+            for (i, &e) in term.exponents.iter().enumerate() {
+                if e > 0 {
+                    factors.push(power(cs, &mut powers, &variables, i, e)?);
+                }
+            }
+
+            let mut acc = (coefficient, term.coefficient);
+            let Some((final_factor, factors)) = factors.split_last() else {
+                // Constant term
+                sum = sum + acc.0;
+                continue;
+            };
+
+            for (k, factor) in factors.iter().enumerate() {
+                let value = mul(acc.1, factor.1);
+                let var = cs.alloc(|| format!("term {} partial {}", t, k), || value.grab())?;
+                cs.enforce(
+                    || format!("term {} partial {} constraint", t, k),
+                    |lc| lc + acc.0,
+                    |lc| lc + factor.0,
+                    |lc| lc + var,
+                );
+                acc = (var, value);
+            }
+
+            // Enforce the previous term's final multiplication, now that it isn't the last
+            if let Some((t, a, b)) = last.replace((t, acc, *final_factor)) {
+                let value = mul(a.1, b.1);
+                let var = cs.alloc(|| format!("term {}", t), || value.grab())?;
+                cs.enforce(
+                    || format!("term {} constraint", t),
+                    |lc| lc + a.0,
+                    |lc| lc + b.0,
+                    |lc| lc + var,
+                );
+                sum = sum + var;
+            }
+        }
+
+        match last {
+            // Enforce the final polynomial constraint: last term = result - other terms
+            Some((_, a, b)) => cs.enforce(
+                || "polynomial constraint",
+                |lc| lc + a.0,
+                |lc| lc + b.0,
+                |lc| lc + result - &sum,
+            ),
+            // Only constant terms
+            None => cs.enforce(
+                || "polynomial constraint",
+                |_| sum,
+                |lc| lc + CS::one(),
+                |lc| lc + result,
+            ),
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(result: u64) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        MultiVarPolynomialCircuit::ax3y2_bx2y_cxy_d(
+            Some(Fr::from(2)),
+            Some(Fr::from(3)),
+            Some(Fr::from(1)),
+            Some(Fr::from(2)),
+            Some(Fr::from(3)),
+            Some(Fr::from(4)),
+            Some(Fr::from(result)),
+        )
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        // 1*8*9 + 2*4*3 + 3*2*3 + 4 = 118
+        let cs = synthesize(118);
+        assert!(cs.is_satisfied());
+        // x^2, x^3, y^2 and two multiplications for each of the three
+        // non-constant terms, the last of which doubles as the sum
+        assert_eq!(cs.num_constraints(), 9);
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let cs = synthesize(117);
+        assert_eq!(cs.which_is_unsatisfied(), Some("polynomial constraint"));
+    }
+
+    #[test]
+    fn powers_are_shared_between_terms() {
+        // x^8 + x^4 y + x^2 z^8 over (x, y, z)
+        let terms: Vec<(Fr, Vec<u32>)> = vec![
+            (Fr::from(5), vec![8, 0, 0]),
+            (Fr::from(6), vec![4, 1, 0]),
+            (Fr::from(7), vec![2, 0, 8]),
+        ];
+        let variables = [Fr::from(3), Fr::from(5), Fr::from(2)];
+
+        let mut cs = TestConstraintSystem::new();
+        MultiVarPolynomialCircuit::new(
+            variables.iter().map(|v| Some(*v)).collect(),
+            terms
+                .iter()
+                .map(|(c, e)| Monomial {
+                    coefficient: Some(*c),
+                    exponents: e.clone(),
+                })
+                .collect(),
+            Some(evaluate(&variables, &terms)),
+        )
+        .unwrap()
+        .synthesize(&mut cs)
+        .unwrap();
+
+        assert!(cs.is_satisfied());
+        // x^2, x^4, x^8 and z^2, z^4, z^8 once each, then 1 + 2 + 2 products
+        assert_eq!(cs.num_constraints(), 6 + 5);
+    }
+
+    #[test]
+    fn constant_polynomial_is_satisfied() {
+        let mut cs = TestConstraintSystem::new();
+        MultiVarPolynomialCircuit {
+            variables: vec![Some(Fr::from(9))],
+            terms: vec![Monomial {
+                coefficient: Some(Fr::from(4)),
+                exponents: vec![0],
+            }],
+            result: Some(Fr::from(4)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+    }
+
+    #[test]
+    fn too_many_exponents_are_rejected() {
+        let terms = vec![
+            Monomial {
+                coefficient: Some(Fr::from(2)),
+                exponents: vec![1],
+            },
+            Monomial {
+                coefficient: Some(Fr::from(3)),
+                exponents: vec![1, 2, 1],
+            },
+        ];
+        let variables = vec![Some(Fr::from(4)), Some(Fr::from(5))];
+
+        let error = MultiVarPolynomialCircuit::new(variables.clone(), terms.clone(), None)
+            .err()
+            .unwrap();
+        assert_eq!(error, "term 1 has 3 exponents for 2 variables");
+
+        // Built by hand, the circuit fails to synthesize instead of panicking
+        let circuit = MultiVarPolynomialCircuit {
+            variables,
+            terms,
+            result: Some(Fr::from(0)),
+        };
+        let mut cs = TestConstraintSystem::new();
+        assert!(matches!(
+            circuit.synthesize(&mut cs),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+}
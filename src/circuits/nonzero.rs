@@ -0,0 +1,91 @@
+use super::boolean::{AllocatedBit, Boolean};
+use super::common::*;
+use bellman::Variable;
+
+// Witness for the inverse of `value`. Zero has no inverse, so it gets zero as
+// a placeholder, which leaves the constraints using it unsatisfied rather than
+// aborting synthesis.
+pub(crate) fn inverse<F: PrimeField>(value: Option<F>) -> Option<F> {
+    value.map(|v| v.invert().unwrap_or(F::ZERO))
+}
+
+// Enforces variable != 0 by requiring an inverse: variable * inv = 1
+pub fn assert_nonzero<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    variable: Variable,
+    value: Option<F>,
+) -> Result<(), SynthesisError> {
+    let inv = cs.alloc(|| "inverse", || inverse(value).grab())?;
+
+    cs.enforce(
+        || "non-zero constraint",
+        |lc| lc + variable,
+        |lc| lc + inv,
+        |lc| lc + CS::one(),
+    );
+
+    Ok(())
+}
+
+// Returns a bit that is 1 iff variable != 0, using
+//   variable * inv = out
+//   variable * (1 - out) = 0
+// If variable is non-zero the second constraint forces out = 1; if it is zero
+// the first forces out = 0, so out is boolean without a separate check.
+pub fn is_nonzero<F: PrimeField, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    variable: Variable,
+    value: Option<F>,
+) -> Result<Boolean, SynthesisError> {
+    let out_value = value.map(|v| !bool::from(v.is_zero()));
+
+    let inv = cs.alloc(|| "inverse", || inverse(value).grab())?;
+    let out = cs.alloc(
+        || "is non-zero",
+        || out_value.map(|b| if b { F::ONE } else { F::ZERO }).grab(),
+    )?;
+
+    cs.enforce(
+        || "inverse constraint",
+        |lc| lc + variable,
+        |lc| lc + inv,
+        |lc| lc + out,
+    );
+    cs.enforce(
+        || "zero constraint",
+        |lc| lc + variable,
+        |lc| lc + CS::one() - out,
+        |lc| lc,
+    );
+
+    Ok(Boolean::Is(AllocatedBit::from_constrained(out, out_value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    #[test]
+    fn assert_nonzero_accepts_nonzero_and_rejects_zero() {
+        for (value, satisfied) in [(Fr::from(7), true), (-Fr::ONE, true), (Fr::ZERO, false)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let x = cs.alloc(|| "x", || Ok(value)).unwrap();
+            assert_nonzero(cs.namespace(|| "x != 0"), x, Some(value)).unwrap();
+            assert_eq!(cs.is_satisfied(), satisfied, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn is_nonzero_computes_the_right_bit() {
+        for (value, expected) in [(Fr::from(7), true), (Fr::ZERO, false)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let x = cs.alloc(|| "x", || Ok(value)).unwrap();
+            let bit = is_nonzero(cs.namespace(|| "x != 0"), x, Some(value)).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(bit.get_value(), Some(expected));
+            assert_eq!(cs.get("x != 0/is non-zero"), Fr::from(expected as u64));
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::common::*;
+use bellman::{LinearCombination, Variable};
+
+// A linear combination of variables together with its value. Unlike bellman's
+// LinearCombination it keeps one term per variable, so repeatedly mixing a few
+// of them, as the linear layers of a hash do, doesn't grow them without bound.
+// Constants are multiples of the constraint system's ONE variable.
+#[derive(Clone, Debug)]
+pub struct Num<F: PrimeField> {
+    terms: Vec<(Variable, F)>,
+    value: Option<F>,
+}
+
+impl<F: PrimeField> Num<F> {
+    pub fn zero() -> Self {
+        Num {
+            terms: vec![],
+            value: Some(F::ZERO),
+        }
+    }
+
+    pub fn from_variable(variable: Variable, value: Option<F>) -> Self {
+        Num {
+            terms: vec![(variable, F::ONE)],
+            value,
+        }
+    }
+
+    // `value` times `one`, which should be CS::one()
+    pub fn constant(one: Variable, value: F) -> Self {
+        Num {
+            terms: vec![(one, value)],
+            value: Some(value),
+        }
+    }
+
+    // Allocates a private variable; no constraints
+    pub fn alloc<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        value: Option<F>,
+    ) -> Result<Self, SynthesisError> {
+        let variable = cs.alloc(|| "num", || value.grab())?;
+        Ok(Self::from_variable(variable, value))
+    }
+
+    pub fn get_value(&self) -> Option<F> {
+        self.value
+    }
+
+    pub fn lc(&self) -> LinearCombination<F> {
+        self.terms
+            .iter()
+            .fold(LinearCombination::zero(), |lc, (v, c)| lc + (*c, *v))
+    }
+
+    // self + coeff * other, merging terms of the same variable
+    pub fn add_scaled(mut self, coeff: F, other: &Self) -> Self {
+        for (variable, c) in &other.terms {
+            match self
+                .terms
+                .iter_mut()
+                .find(|(v, _)| v.get_unchecked() == variable.get_unchecked())
+            {
+                Some((_, existing)) => *existing += coeff * c,
+                None => self.terms.push((*variable, coeff * c)),
+            }
+        }
+        self.value = match (self.value, other.value) {
+            (Some(a), Some(b)) => Some(a + coeff * b),
+            _ => None,
+        };
+        self
+    }
+
+    pub fn plus(self, other: &Self) -> Self {
+        self.add_scaled(F::ONE, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    #[test]
+    fn terms_of_a_variable_are_merged() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let x = Num::alloc(cs.namespace(|| "x"), Some(Fr::from(3))).unwrap();
+        let y = Num::alloc(cs.namespace(|| "y"), Some(Fr::from(5))).unwrap();
+
+        // Mixing x and y over and over stays at two terms
+        let (mut a, mut b) = (x.clone(), y.clone());
+        for _ in 0..50 {
+            (a, b) = (a.clone().plus(&b), b.add_scaled(Fr::from(2), &a));
+        }
+        assert_eq!(a.terms.len(), 2);
+        assert_eq!(b.lc().as_ref().len(), 2);
+
+        // x + 2 - y = 0
+        let one = TestConstraintSystem::<Fr>::one();
+        let sum = x
+            .add_scaled(Fr::from(2), &Num::constant(one, Fr::ONE))
+            .add_scaled(-Fr::ONE, &y);
+        assert_eq!(sum.get_value(), Some(Fr::ZERO));
+        cs.enforce(|| "sum", |_| sum.lc(), |lc| lc + one, |lc| lc);
+        assert!(cs.is_satisfied());
+    }
+}
@@ -1,6 +1,7 @@
 use super::common::*;
 
 // Circuit is: 2 * x_squared + 3y + 5 = z
+#[derive(Clone)]
 pub struct PolynomialCircuit<F: PrimeField> {
     pub x: Option<F>,
     pub y: Option<F>,
@@ -37,4 +38,37 @@ impl<F: PrimeField> Circuit<F> for PolynomialCircuit<F> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(x: u64, y: u64, z: u64) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        PolynomialCircuit {
+            x: Some(Fr::from(x)),
+            y: Some(Fr::from(y)),
+            z: Some(Fr::from(z)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        let cs = synthesize(256, 729, 133264);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 2);
+        assert!(cs.verify(&[Fr::from(133264)]));
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let cs = synthesize(256, 729, 133265);
+        assert_eq!(cs.which_is_unsatisfied(), Some("z constraint"));
+    }
+}
@@ -0,0 +1,427 @@
+use super::common::*;
+use super::num::Num;
+
+// Poseidon (Grassi et al. 2019) with width 3 (rate 2, capacity 1) and the
+// x^5 S-box, which is a permutation of the BLS12-381 scalar field since
+// gcd(5, p - 1) = 1. 8 full and 57 partial rounds are the paper's numbers for
+// a 255-bit field at the 128-bit security level with these choices.
+pub const WIDTH: usize = 3;
+pub const FULL_ROUNDS: usize = 8;
+pub const PARTIAL_ROUNDS: usize = 57;
+
+// Each S-box costs 3 constraints: x^2, x^4 and x^5
+pub const CONSTRAINTS_PER_PERMUTATION: usize = 3 * (FULL_ROUNDS * WIDTH + PARTIAL_ROUNDS);
+
+// The round constants and MDS matrix of the permutation
+#[derive(Clone, Debug)]
+pub struct Poseidon<F: PrimeField> {
+    round_constants: Vec<[F; WIDTH]>,
+    mds: [[F; WIDTH]; WIDTH],
+}
+
+impl<F: PrimeField> Default for Poseidon<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The Grain LFSR the reference implementation (generate_parameters_grain.sage)
+// draws its parameters from, seeded with the field, S-box, field size, width
+// and round numbers
+struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    fn new(num_bits: u32) -> Self {
+        // Field type 1 (prime) and S-box 0 (x^alpha), then the sizes, each
+        // most significant bit first, padded with ones to 80 bits
+        let fields = [
+            (1, 2),
+            (0, 4),
+            (num_bits as usize, 12),
+            (WIDTH, 12),
+            (FULL_ROUNDS, 10),
+            (PARTIAL_ROUNDS, 10),
+        ];
+        let mut seed = vec![];
+        for (value, width) in fields {
+            seed.extend((0..width).rev().map(|i| value >> i & 1 == 1));
+        }
+        seed.resize(80, true);
+
+        let mut grain = Grain {
+            state: seed.try_into().unwrap(),
+        };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    // Bits are drawn in pairs, keeping the second one if the first is set
+    fn next_bit(&mut self) -> bool {
+        while !self.step() {
+            self.step();
+        }
+        self.step()
+    }
+
+    // `num_bits` bits, most significant first
+    fn next_bits(&mut self, num_bits: u32) -> Vec<bool> {
+        (0..num_bits).map(|_| self.next_bit()).collect()
+    }
+}
+
+fn from_bits<F: PrimeField>(bits: &[bool]) -> F {
+    bits.iter().fold(F::ZERO, |acc, bit| acc.double() + F::from(*bit as u64))
+}
+
+// Whether the big-endian `bits` are below the modulus
+fn below_modulus<F: PrimeField>(bits: &[bool]) -> bool {
+    let modulus = F::MODULUS.trim_start_matches("0x");
+    let modulus = modulus.chars().flat_map(|c| {
+        let digit = c.to_digit(16).unwrap();
+        (0..4).rev().map(move |i| digit >> i & 1 == 1)
+    });
+    let modulus: Vec<bool> = modulus.skip_while(|bit| !bit).collect();
+    (bits.len(), bits) < (modulus.len(), modulus.as_slice())
+}
+
+impl<F: PrimeField> Poseidon<F> {
+    // The parameters of the reference implementation: the round constants
+    // are uniform draws from the Grain LFSR, rejecting those not below the
+    // modulus, and the MDS matrix is the Cauchy matrix 1 / (x_i + y_j) of the
+    // draws that follow. The permutation matches the reference test vector
+    // for x^5, a 255-bit field and width 3, see the tests.
+    pub fn new() -> Self {
+        let mut grain = Grain::new(F::NUM_BITS);
+        let mut constant = || loop {
+            let bits = grain.next_bits(F::NUM_BITS);
+            if below_modulus::<F>(&bits) {
+                return from_bits::<F>(&bits);
+            }
+        };
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|_| std::array::from_fn(|_| constant()))
+            .collect();
+
+        // The reference draws again on duplicates or a zero sum, which
+        // doesn't happen with these parameters
+        let xy: [F; 2 * WIDTH] = std::array::from_fn(|_| from_bits(&grain.next_bits(F::NUM_BITS)));
+        let mds = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (xy[i] + xy[WIDTH + j]).invert().unwrap())
+        });
+
+        Poseidon {
+            round_constants,
+            mds,
+        }
+    }
+
+    fn is_full_round(r: usize) -> bool {
+        !(FULL_ROUNDS / 2..FULL_ROUNDS / 2 + PARTIAL_ROUNDS).contains(&r)
+    }
+
+    pub fn permute(&self, state: &mut [F; WIDTH]) {
+        for (r, constants) in self.round_constants.iter().enumerate() {
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s += c;
+            }
+
+            let sbox = |x: F| x.square().square() * x;
+            if Self::is_full_round(r) {
+                state.iter_mut().for_each(|s| *s = sbox(*s));
+            } else {
+                state[0] = sbox(state[0]);
+            }
+
+            *state = std::array::from_fn(|i| (0..WIDTH).map(|j| self.mds[i][j] * state[j]).sum());
+        }
+    }
+
+    // Sponge over the permutation: the capacity starts at the input length,
+    // which tells apart inputs that only differ by the zero padding of the
+    // last chunk. Absorbs two elements per permutation and squeezes one. The
+    // permutation is the reference one, but this padding is our own, so
+    // hashes won't match other Poseidon sponges.
+    pub fn hash(&self, inputs: &[F]) -> F {
+        let mut state = [F::from(inputs.len() as u64), F::ZERO, F::ZERO];
+        for chunk in chunks(inputs.len()) {
+            for (s, i) in state[1..].iter_mut().zip(chunk) {
+                *s += inputs[i];
+            }
+            self.permute(&mut state);
+        }
+        state[1]
+    }
+
+    // x^5 in three constraints
+    fn sbox_gadget<CS: ConstraintSystem<F>>(
+        mut cs: CS,
+        x: &Num<F>,
+    ) -> Result<Num<F>, SynthesisError> {
+        let x2_value = x.get_value().map(|x| x.square());
+        let x4_value = x2_value.map(|x2| x2.square());
+        let x5_value = x4_value.and_then(|x4| Some(x4 * x.get_value()?));
+
+        let x2 = cs.alloc(|| "x^2", || x2_value.grab())?;
+        let x4 = cs.alloc(|| "x^4", || x4_value.grab())?;
+        let x5 = cs.alloc(|| "x^5", || x5_value.grab())?;
+        cs.enforce(|| "x^2 constraint", |_| x.lc(), |_| x.lc(), |lc| lc + x2);
+        cs.enforce(|| "x^4 constraint", |lc| lc + x2, |lc| lc + x2, |lc| lc + x4);
+        cs.enforce(|| "x^5 constraint", |lc| lc + x4, |_| x.lc(), |lc| lc + x5);
+
+        Ok(Num::from_variable(x5, x5_value))
+    }
+
+    // The permutation in-circuit. Adding constants and the MDS layer are free;
+    // only the S-boxes add constraints.
+    pub fn permute_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        state: [Num<F>; WIDTH],
+    ) -> Result<[Num<F>; WIDTH], SynthesisError> {
+        let mut state = state;
+        for (r, constants) in self.round_constants.iter().enumerate() {
+            let mut cs = cs.namespace(|| format!("round {}", r));
+            for (s, c) in state.iter_mut().zip(constants) {
+                *s = s.clone().plus(&Num::constant(CS::one(), *c));
+            }
+
+            let sboxes = if Self::is_full_round(r) { WIDTH } else { 1 };
+            for (i, s) in state.iter_mut().enumerate().take(sboxes) {
+                *s = Self::sbox_gadget(cs.namespace(|| format!("sbox {}", i)), s)?;
+            }
+
+            state = std::array::from_fn(|i| {
+                (0..WIDTH).fold(Num::zero(), |acc, j| acc.add_scaled(self.mds[i][j], &state[j]))
+            });
+        }
+        Ok(state)
+    }
+
+    // `hash` in-circuit
+    pub fn hash_gadget<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+        inputs: &[Num<F>],
+    ) -> Result<Num<F>, SynthesisError> {
+        let length = Num::constant(CS::one(), F::from(inputs.len() as u64));
+        let mut state = [length, Num::zero(), Num::zero()];
+        for (n, chunk) in chunks(inputs.len()).enumerate() {
+            for (s, i) in state[1..].iter_mut().zip(chunk) {
+                *s = s.clone().plus(&inputs[i]);
+            }
+            state = self.permute_gadget(cs.namespace(|| format!("permutation {}", n)), state)?;
+        }
+        let [_, output, _] = state;
+        Ok(output)
+    }
+}
+
+// Index ranges of the chunks absorbed per permutation; at least one, so the
+// empty input is hashed too
+fn chunks(len: usize) -> impl Iterator<Item = std::ops::Range<usize>> {
+    let rate = WIDTH - 1;
+    (0..len.div_ceil(rate).max(1)).map(move |c| c * rate..((c + 1) * rate).min(len))
+}
+
+// Circuit: knowledge of a preimage of field elements with a given Poseidon
+// hash. The preimage is private and its length fixes the circuit shape; the
+// hash is the only public input.
+#[derive(Clone)]
+pub struct PoseidonPreimageCircuit<F: PrimeField> {
+    pub preimage: Vec<Option<F>>,
+    pub hash: Option<F>,
+}
+
+impl<F: PrimeField> Circuit<F> for PoseidonPreimageCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let preimage = self
+            .preimage
+            .iter()
+            .enumerate()
+            .map(|(i, x)| Num::alloc(cs.namespace(|| format!("preimage {}", i)), *x))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hash = cs.alloc_input(|| "hash", || self.hash.grab())?;
+
+        let output = Poseidon::new().hash_gadget(cs.namespace(|| "poseidon"), &preimage)?;
+        cs.enforce(
+            || "hash constraint",
+            |_| output.lc(),
+            |lc| lc + CS::one(),
+            |lc| lc + hash,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn elements(n: u64) -> Vec<Fr> {
+        (1..=n).map(|i| Fr::from(i * 1000 + 7)).collect()
+    }
+
+    // Hex literal, most significant digit first
+    fn hex(s: &str) -> Fr {
+        s.trim_start_matches("0x").chars().fold(Fr::ZERO, |acc, c| {
+            acc * Fr::from(16) + Fr::from(c.to_digit(16).unwrap() as u64)
+        })
+    }
+
+    type Matrix = [[Fr; WIDTH]; WIDTH];
+
+    fn det2(m: &Matrix, rows: [usize; 2], cols: [usize; 2]) -> Fr {
+        m[rows[0]][cols[0]] * m[rows[1]][cols[1]] - m[rows[0]][cols[1]] * m[rows[1]][cols[0]]
+    }
+
+    fn det3(m: &Matrix) -> Fr {
+        m[0][0] * det2(m, [1, 2], [1, 2]) - m[0][1] * det2(m, [1, 2], [0, 2])
+            + m[0][2] * det2(m, [1, 2], [0, 1])
+    }
+
+    fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+        std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..WIDTH).map(|k| a[i][k] * b[k][j]).sum())
+        })
+    }
+
+    #[test]
+    fn permutation_matches_the_reference_vector() {
+        // poseidonperm_x5_255_3 from the reference implementation
+        let poseidon = Poseidon::<Fr>::new();
+        let mut state = [Fr::from(0), Fr::from(1), Fr::from(2)];
+        poseidon.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                hex("0x28ce19420fc246a05553ad1e8c98f5c9d67166be2c18e9e4cb4b4e317dd2a78a"),
+                hex("0x51f3e312c95343a896cfd8945ea82ba956c1118ce9b9859b6ea56637b4b1ddc4"),
+                hex("0x3b2b69139b235626a0bfb56c9527ae66a7bf486ad8c11c14d1da0c69bbe0f79a"),
+            ]
+        );
+
+        assert_eq!(
+            poseidon.round_constants[0][0],
+            hex("0x6c4ffa723eaf1a7bf74905cc7dae4ca9ff4a2c3bc81d42e09540d1f250910880")
+        );
+        assert_eq!(
+            poseidon.mds[0][0],
+            hex("0x3d955d6c02fe4d7cb500e12f2b55eff668a7b4386bd27413766713c93f2acfcd")
+        );
+    }
+
+    #[test]
+    fn mds_matrix_is_mds_and_has_no_invariant_trails() {
+        let mds = Poseidon::<Fr>::new().mds;
+
+        // Every square submatrix is invertible, the whole matrix included
+        let pairs = [[0, 1], [0, 2], [1, 2]];
+        assert!(mds.iter().flatten().all(|x| !bool::from(x.is_zero())));
+        for rows in pairs {
+            assert!(pairs.iter().all(|cols| !bool::from(det2(&mds, rows, *cols).is_zero())));
+        }
+        assert!(!bool::from(det3(&mds).is_zero()));
+
+        // The partial rounds only touch the first element. A subspace that M^l
+        // maps into itself and that keeps the first element at zero would skip
+        // their S-boxes for good; the largest such subspace is the kernel of
+        // the rows e_0, e_0 M^l, e_0 M^2l, so those have to be independent.
+        let mut power = mds;
+        for l in 1..=2 * WIDTH {
+            let mut rows = [[Fr::ONE, Fr::ZERO, Fr::ZERO]; WIDTH];
+            for k in 1..WIDTH {
+                let row = rows[k - 1];
+                rows[k] = std::array::from_fn(|j| (0..WIDTH).map(|i| row[i] * power[i][j]).sum());
+            }
+            assert!(!bool::from(det3(&rows).is_zero()), "M^{}", l);
+            power = mul(&power, &mds);
+        }
+    }
+
+    #[test]
+    fn gadget_matches_native_permutation() {
+        let poseidon = Poseidon::<Fr>::new();
+        let input = [Fr::from(1), Fr::from(2), -Fr::from(3)];
+        let mut expected = input;
+        poseidon.permute(&mut expected);
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let state = std::array::from_fn(|i| {
+            Num::alloc(cs.namespace(|| format!("input {}", i)), Some(input[i])).unwrap()
+        });
+        let output = poseidon.permute_gadget(cs.namespace(|| "permutation"), state).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), CONSTRAINTS_PER_PERMUTATION);
+        assert_eq!(output.map(|o| o.get_value().unwrap()), expected);
+    }
+
+    #[test]
+    fn gadget_matches_native_hash() {
+        let poseidon = Poseidon::<Fr>::new();
+        for n in 0..6 {
+            let preimage = elements(n);
+            let hash = poseidon.hash(&preimage);
+
+            let mut cs = TestConstraintSystem::new();
+            PoseidonPreimageCircuit {
+                preimage: preimage.iter().copied().map(Some).collect(),
+                hash: Some(hash),
+            }
+            .synthesize(&mut cs)
+            .unwrap();
+            assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+            assert!(cs.verify(&[hash]));
+
+            // One permutation per two elements, and the equality check
+            let permutations = (n as usize).div_ceil(2).max(1);
+            assert_eq!(cs.num_constraints(), permutations * CONSTRAINTS_PER_PERMUTATION + 1);
+        }
+    }
+
+    #[test]
+    fn wrong_hash_is_rejected() {
+        let poseidon = Poseidon::<Fr>::new();
+        let preimage = elements(3);
+        let hash = poseidon.hash(&preimage);
+
+        let mut cs = TestConstraintSystem::new();
+        PoseidonPreimageCircuit {
+            preimage: preimage.iter().copied().map(Some).collect(),
+            hash: Some(hash + Fr::ONE),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("hash constraint"));
+    }
+
+    #[test]
+    fn padding_and_length_change_the_hash() {
+        let poseidon = Poseidon::<Fr>::new();
+        let hashes = [
+            poseidon.hash(&[]),
+            poseidon.hash(&[Fr::ZERO]),
+            poseidon.hash(&[Fr::ZERO, Fr::ZERO]),
+            poseidon.hash(&[Fr::from(1), Fr::from(2)]),
+            poseidon.hash(&[Fr::from(2), Fr::from(1)]),
+        ];
+        for (i, a) in hashes.iter().enumerate() {
+            assert!(hashes[i + 1..].iter().all(|b| a != b), "{}", i);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use super::boolean::AllocatedBit;
+use super::common::*;
+use super::num::Num;
+use bellman::LinearCombination;
+
+// Enforces 0 <= lc < 2^num_bits by decomposing `value` into little-endian
+// bits and requiring sum(2^i * b_i) = lc. Costs num_bits + 1 constraints.
+// A value that needs more bits can't be packed back, so the final
+// constraint fails.
+pub fn enforce_bits<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    lc: &LinearCombination<F>,
+    value: Option<F>,
+    num_bits: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    // More bits than the capacity would let the packing wrap around the modulus
+    assert!(num_bits <= F::CAPACITY as usize);
+
+    let bit_values: Vec<Option<bool>> = match value {
+        Some(v) => v.to_le_bits().iter().take(num_bits).map(|b| Some(*b)).collect(),
+        None => vec![None; num_bits],
+    };
+
+    let bits = bit_values
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), b))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = F::ONE;
+    for bit in &bits {
+        packed = packed + (coeff, bit.get_variable());
+        coeff = coeff.double();
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |_| packed,
+        |lc| lc + CS::one(),
+        |_| lc.clone(),
+    );
+
+    Ok(bits)
+}
+
+// Enforces lo <= x <= hi by range checking x - lo and hi - x to num_bits
+// bits each, 2 * (num_bits + 1) constraints. Both differences are below
+// 2^num_bits and add up to hi - lo, so with bounds below p - 2^num_bits this
+// is the integer comparison; it needs hi - lo < 2^num_bits to be satisfiable.
+pub fn enforce_between<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    x: &Num<F>,
+    lo: &Num<F>,
+    hi: &Num<F>,
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    // The two differences have to add up without wrapping around
+    assert!(num_bits < F::CAPACITY as usize);
+
+    let above = x.clone().add_scaled(-F::ONE, lo);
+    let below = hi.clone().add_scaled(-F::ONE, x);
+    enforce_bits(cs.namespace(|| "lower bound"), &above.lc(), above.get_value(), num_bits)?;
+    enforce_bits(cs.namespace(|| "upper bound"), &below.lc(), below.get_value(), num_bits)?;
+
+    Ok(())
+}
+
+// Enforces lo <= x <= hi for constant bounds, with as many bits as hi - lo
+// needs. Panics unless lo <= hi.
+pub fn enforce_in_range<F: PrimeFieldBits, CS: ConstraintSystem<F>>(
+    cs: CS,
+    x: &Num<F>,
+    lo: F,
+    hi: F,
+) -> Result<(), SynthesisError> {
+    // hi < lo wraps around to a width of about p
+    let num_bits = (hi - lo).to_le_bits().last_one().map_or(0, |i| i + 1);
+    assert!(num_bits < F::CAPACITY as usize, "empty or too wide range");
+
+    let (lo, hi) = (Num::constant(CS::one(), lo), Num::constant(CS::one(), hi));
+    enforce_between(cs, x, &lo, &hi, num_bits)
+}
+
+// Circuit: a private value lies in [lo, hi], where the bounds are public
+// inputs and num_bits bounds the width of the range and fixes the shape.
+// lo = 0 and hi = 2^num_bits - 1 show 0 <= x < 2^num_bits.
+#[derive(Clone)]
+pub struct RangeProofCircuit<F: PrimeField> {
+    pub value: Option<F>,
+    pub lo: Option<F>,
+    pub hi: Option<F>,
+    pub num_bits: usize,
+}
+
+impl<F: PrimeFieldBits> Circuit<F> for RangeProofCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let value = Num::alloc(cs.namespace(|| "value"), self.value)?;
+        let lo = cs.alloc_input(|| "lo", || self.lo.grab())?;
+        let hi = cs.alloc_input(|| "hi", || self.hi.grab())?;
+
+        enforce_between(
+            cs.namespace(|| "range"),
+            &value,
+            &Num::from_variable(lo, self.lo),
+            &Num::from_variable(hi, self.hi),
+            self.num_bits,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn check(value: Fr, num_bits: usize) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        let x = cs.alloc(|| "x", || Ok(value)).unwrap();
+        enforce_bits(
+            cs.namespace(|| "range"),
+            &(LinearCombination::zero() + x),
+            Some(value),
+            num_bits,
+        )
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn values_in_range_are_accepted() {
+        for value in [0, 1, 200, 255] {
+            let cs = check(Fr::from(value), 8);
+            assert!(cs.is_satisfied(), "{}", value);
+            assert_eq!(cs.num_constraints(), 9);
+        }
+    }
+
+    #[test]
+    fn values_out_of_range_are_rejected() {
+        for value in [Fr::from(256), Fr::from(1000), -Fr::ONE] {
+            let cs = check(value, 8);
+            assert_eq!(cs.which_is_unsatisfied(), Some("range/packing constraint"));
+        }
+    }
+
+    fn check_in_range(value: Fr, lo: u64, hi: u64) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        let x = Num::alloc(cs.namespace(|| "x"), Some(value)).unwrap();
+        enforce_in_range(cs.namespace(|| "range"), &x, Fr::from(lo), Fr::from(hi)).unwrap();
+        cs
+    }
+
+    #[test]
+    fn interval_bounds_are_inclusive() {
+        for value in [100, 101, 150, 199, 200] {
+            let cs = check_in_range(Fr::from(value), 100, 200);
+            assert!(cs.is_satisfied(), "{}", value);
+            // 100 needs 7 bits, twice plus the packing constraints
+            assert_eq!(cs.num_constraints(), 16);
+        }
+
+        let cs = check_in_range(Fr::from(99), 100, 200);
+        assert_eq!(cs.which_is_unsatisfied(), Some("range/lower bound/packing constraint"));
+        for value in [Fr::from(201), Fr::from(1 << 20), -Fr::ONE] {
+            let cs = check_in_range(value, 100, 200);
+            assert!(!cs.is_satisfied(), "{:?}", value);
+        }
+
+        // A single point needs no bits
+        assert!(check_in_range(Fr::from(7), 7, 7).is_satisfied());
+        assert!(!check_in_range(Fr::from(8), 7, 7).is_satisfied());
+    }
+
+    #[test]
+    #[should_panic(expected = "empty or too wide range")]
+    fn empty_interval_panics() {
+        check_in_range(Fr::from(5), 6, 5);
+    }
+
+    fn prove_range(value: u128, lo: u128, hi: u128, num_bits: usize) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        RangeProofCircuit {
+            value: Some(Fr::from_u128(value)),
+            lo: Some(Fr::from_u128(lo)),
+            hi: Some(Fr::from_u128(hi)),
+            num_bits,
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn range_proofs_check_both_bounds() {
+        for num_bits in [8, 32, 64, 128] {
+            let hi = u128::MAX >> (128 - num_bits);
+            for value in [0, 1, hi / 3, hi] {
+                let cs = prove_range(value, 0, hi, num_bits);
+                assert!(cs.is_satisfied(), "{} {}", num_bits, value);
+                assert!(cs.verify(&[Fr::ZERO, Fr::from_u128(hi)]));
+                assert_eq!(cs.num_constraints(), 2 * (num_bits + 1));
+            }
+        }
+
+        let cs = prove_range(1000, 1001, 2000, 16);
+        assert_eq!(cs.which_is_unsatisfied(), Some("range/lower bound/packing constraint"));
+        let cs = prove_range(2001, 1001, 2000, 16);
+        assert_eq!(cs.which_is_unsatisfied(), Some("range/upper bound/packing constraint"));
+
+        // Too wide a range for the bits can't be proven even for values in it
+        let cs = prove_range(300, 0, 1000, 8);
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -0,0 +1,169 @@
+use super::common::*;
+use bellman::gadgets::boolean::{AllocatedBit, Boolean};
+use bellman::gadgets::{multipack, sha256::sha256 as sha256_gadget};
+
+// Circuit: knowledge of a preimage x with SHA-256(x) = digest, built from
+// bellman's bit-level SHA-256 gadget. The preimage is private and its length
+// in bytes fixes the circuit shape. The 256 digest bits are packed into public
+// inputs, CAPACITY bits each, so two inputs over BLS12-381.
+#[derive(Clone)]
+pub struct Sha256PreimageCircuit {
+    pub preimage: Option<Vec<u8>>,
+    pub len: usize,
+}
+
+impl<F: PrimeField> Circuit<F> for Sha256PreimageCircuit {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if let Some(preimage) = &self.preimage {
+            assert_eq!(preimage.len(), self.len, "preimage length doesn't match the shape");
+        }
+
+        // Big-endian bits within each byte, as the gadget expects
+        let bit_values = match &self.preimage {
+            Some(preimage) => multipack::bytes_to_bits(preimage).into_iter().map(Some).collect(),
+            None => vec![None; 8 * self.len],
+        };
+        let bits = bit_values
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| {
+                AllocatedBit::alloc(cs.namespace(|| format!("preimage bit {}", i)), b)
+                    .map(Boolean::from)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let digest = sha256_gadget(cs.namespace(|| "sha256"), &bits)?;
+        multipack::pack_into_inputs(cs.namespace(|| "digest"), &digest)?;
+
+        Ok(())
+    }
+}
+
+// The public inputs of the circuit for `digest`
+pub fn digest_inputs<F: PrimeField>(digest: &[u8; 32]) -> Vec<F> {
+    multipack::compute_multipacking(&multipack::bytes_to_bits(digest))
+}
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// Native SHA-256 (FIPS 180-4), to compute the digest the circuit is proven
+// against
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    // Padding: a 1 bit, zeros up to 56 mod 64 bytes, then the bit length
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((8 * data.len() as u64).to_be_bytes());
+
+    let mut state = INITIAL_STATE;
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(ROUND_CONSTANTS[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            (h, g, f, e) = (g, f, e, d.wrapping_add(t1));
+            (d, c, b, a) = (c, b, a, t1.wrapping_add(t2));
+        }
+
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn native_hash_matches_test_vectors() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks after padding
+        assert_eq!(
+            hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    fn synthesize(preimage: &[u8]) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        Sha256PreimageCircuit {
+            preimage: Some(preimage.to_vec()),
+            len: preimage.len(),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn circuit_digest_matches_native_hash() {
+        for preimage in [&b"abc"[..], &[7; 64], &[0xff; 100]] {
+            let cs = synthesize(preimage);
+            assert!(cs.is_satisfied(), "{:?}", cs.which_is_unsatisfied());
+
+            let inputs: Vec<Fr> = digest_inputs(&sha256(preimage));
+            assert_eq!(inputs.len(), 2);
+            assert!(cs.verify(&inputs), "{}", preimage.len());
+        }
+    }
+
+    #[test]
+    fn other_digest_is_rejected() {
+        let cs = synthesize(b"abc");
+        assert!(!cs.verify(&digest_inputs(&sha256(b"abd"))));
+    }
+}
@@ -0,0 +1,227 @@
+use super::boolean::AllocatedBit;
+use super::common::*;
+use bellman::LinearCombination;
+
+pub type Grid<T> = [[T; 9]; 9];
+
+// Circuit: knowledge of a solution to a 9x9 Sudoku. The puzzle is public, one
+// input per cell in row-major order with 0 for blanks; the solution is private.
+// Each solution cell is one-hot encoded as nine bits, which range checks it to
+// 1..=9, and every row, column and box then has to contain each digit exactly
+// once. 81 * 11 + 27 * 9 = 1134 constraints.
+#[derive(Clone)]
+pub struct SudokuCircuit<F: PrimeField> {
+    pub puzzle: Grid<Option<F>>,
+    pub solution: Grid<Option<F>>,
+}
+
+// The cells of each row, column and 3x3 box
+fn units() -> Vec<Vec<(usize, usize)>> {
+    let rows = (0..9).map(|r| (0..9).map(|c| (r, c)).collect());
+    let cols = (0..9).map(|c| (0..9).map(|r| (r, c)).collect());
+    let boxes = (0..9).map(|b| (0..9).map(|i| (b / 3 * 3 + i / 3, b % 3 * 3 + i % 3)).collect());
+    rows.chain(cols).chain(boxes).collect()
+}
+
+impl<F: PrimeField> Circuit<F> for SudokuCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut digits: Vec<Vec<[AllocatedBit; 9]>> = Vec::with_capacity(9);
+
+        for r in 0..9 {
+            let mut row = Vec::with_capacity(9);
+            for c in 0..9 {
+                let mut cs = cs.namespace(|| format!("cell {} {}", r, c));
+                let given = cs.alloc_input(|| "puzzle", || self.puzzle[r][c].grab())?;
+
+                // bits[d] is set iff the cell holds d + 1; a value outside
+                // 1..=9 sets none of them and fails the one-hot constraint
+                let value = self.solution[r][c];
+                let bits = (0..9)
+                    .map(|d| {
+                        let bit = value.map(|v| v == F::from(d as u64 + 1));
+                        AllocatedBit::alloc(cs.namespace(|| format!("digit {}", d + 1)), bit)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                cs.enforce(
+                    || "one-hot constraint",
+                    |lc| bits.iter().fold(lc, |lc, b| lc + b.get_variable()),
+                    |lc| lc + CS::one(),
+                    |lc| lc + CS::one(),
+                );
+
+                // given * (cell - given) = 0: blanks are free, clues are kept
+                let cell = bits
+                    .iter()
+                    .enumerate()
+                    .fold(LinearCombination::zero(), |lc, (d, b)| {
+                        lc + (F::from(d as u64 + 1), b.get_variable())
+                    });
+                cs.enforce(
+                    || "clue constraint",
+                    |lc| lc + given,
+                    |_| cell - given,
+                    |lc| lc,
+                );
+
+                row.push(bits.try_into().unwrap());
+            }
+            digits.push(row);
+        }
+
+        // With every cell holding exactly one digit, each digit appearing once
+        // per unit makes the unit a permutation of 1..=9
+        for (u, unit) in units().iter().enumerate() {
+            let cells: Vec<&[AllocatedBit; 9]> = unit.iter().map(|&(r, c)| &digits[r][c]).collect();
+            for d in 0..9 {
+                cs.enforce(
+                    || format!("unit {} digit {}", u, d + 1),
+                    |lc| cells.iter().fold(lc, |lc, bits| lc + bits[d].get_variable()),
+                    |lc| lc + CS::one(),
+                    |lc| lc + CS::one(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Digits that can go in (r, c) without repeating one in its row, column or box
+fn candidates(grid: &Grid<u8>, r: usize, c: usize) -> Vec<u8> {
+    (1..=9)
+        .filter(|&d| {
+            (0..9).all(|i| {
+                let (box_r, box_c) = (r / 3 * 3 + i / 3, c / 3 * 3 + i % 3);
+                grid[r][i] != d && grid[i][c] != d && grid[box_r][box_c] != d
+            })
+        })
+        .collect()
+}
+
+// Fills the blank with the fewest candidates first, backtracking on dead ends
+fn search(grid: &mut Grid<u8>) -> bool {
+    let blank = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| grid[r][c] == 0)
+        .map(|(r, c)| (r, c, candidates(grid, r, c)))
+        .min_by_key(|(_, _, digits)| digits.len());
+
+    match blank {
+        None => true,
+        Some((r, c, digits)) => {
+            for d in digits {
+                grid[r][c] = d;
+                if search(grid) {
+                    return true;
+                }
+            }
+            grid[r][c] = 0;
+            false
+        }
+    }
+}
+
+// Solves a puzzle, 0 for blanks. Returns None if it has no solution.
+pub fn solve(puzzle: &Grid<u8>) -> Option<Grid<u8>> {
+    let mut grid = *puzzle;
+
+    // The clues have to be consistent among themselves, since the search only
+    // checks the digits it places
+    for r in 0..9 {
+        for c in 0..9 {
+            let d = grid[r][c];
+            if d != 0 {
+                grid[r][c] = 0;
+                if !candidates(&grid, r, c).contains(&d) {
+                    return None;
+                }
+                grid[r][c] = d;
+            }
+        }
+    }
+
+    search(&mut grid).then_some(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    const PUZZLE: Grid<u8> = [
+        [5, 3, 0, 0, 7, 0, 0, 0, 0],
+        [6, 0, 0, 1, 9, 5, 0, 0, 0],
+        [0, 9, 8, 0, 0, 0, 0, 6, 0],
+        [8, 0, 0, 0, 6, 0, 0, 0, 3],
+        [4, 0, 0, 8, 0, 3, 0, 0, 1],
+        [7, 0, 0, 0, 2, 0, 0, 0, 6],
+        [0, 6, 0, 0, 0, 0, 2, 8, 0],
+        [0, 0, 0, 4, 1, 9, 0, 0, 5],
+        [0, 0, 0, 0, 8, 0, 0, 7, 9],
+    ];
+
+    fn to_fr(grid: &Grid<u8>) -> Grid<Option<Fr>> {
+        grid.map(|row| row.map(|d| Some(Fr::from(d as u64))))
+    }
+
+    fn synthesize(puzzle: &Grid<u8>, solution: &Grid<u8>) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        SudokuCircuit {
+            puzzle: to_fr(puzzle),
+            solution: to_fr(solution),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn solution_is_accepted() {
+        let solution = solve(&PUZZLE).unwrap();
+        assert_eq!(solution[0], [5, 3, 4, 6, 7, 8, 9, 1, 2]);
+
+        let cs = synthesize(&PUZZLE, &solution);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1134);
+        let inputs: Vec<Fr> = PUZZLE.iter().flatten().map(|&d| Fr::from(d as u64)).collect();
+        assert!(cs.verify(&inputs));
+    }
+
+    #[test]
+    fn invalid_solutions_are_rejected() {
+        let solution = solve(&PUZZLE).unwrap();
+
+        // Swapping two cells of a row keeps the row valid but breaks columns
+        let mut swapped = solution;
+        swapped[8].swap(0, 1);
+        let cs = synthesize(&PUZZLE, &swapped);
+        assert_eq!(cs.which_is_unsatisfied(), Some("unit 9 digit 3"));
+
+        // Ignoring a clue
+        let mut blank = [[0; 9]; 9];
+        blank[0][0] = 1;
+        let cs = synthesize(&blank, &solution);
+        assert_eq!(cs.which_is_unsatisfied(), Some("cell 0 0/clue constraint"));
+
+        // Out of range
+        let mut ten = solution;
+        ten[4][4] = 10;
+        let cs = synthesize(&[[0; 9]; 9], &ten);
+        assert_eq!(cs.which_is_unsatisfied(), Some("cell 4 4/one-hot constraint"));
+    }
+
+    #[test]
+    fn inconsistent_puzzles_have_no_solution() {
+        let mut puzzle = PUZZLE;
+        puzzle[0][2] = 5; // twice in the first row
+        assert_eq!(solve(&puzzle), None);
+
+        // A blank with no candidates
+        let mut puzzle = [[0; 9]; 9];
+        puzzle[0][..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        puzzle[1][8] = 9;
+        assert_eq!(solve(&puzzle), None);
+    }
+}
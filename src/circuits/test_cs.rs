@@ -0,0 +1,195 @@
+use super::common::*;
+use bellman::{Index, LinearCombination, Variable};
+
+// A * B = C, along with the constraint's namespace path
+type NamedConstraint<F> = (
+    LinearCombination<F>,
+    LinearCombination<F>,
+    LinearCombination<F>,
+    String,
+);
+
+// A constraint system that records concrete assignments and checks every
+// constraint against them, so circuits can be tested without running Groth16.
+// Constraints are named by their full namespace path, e.g. "outer/inner/name".
+pub struct TestConstraintSystem<F: PrimeField> {
+    inputs: Vec<(F, String)>,
+    aux: Vec<(F, String)>,
+    constraints: Vec<NamedConstraint<F>>,
+    current_namespace: Vec<String>,
+}
+
+impl<F: PrimeField> Default for TestConstraintSystem<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: PrimeField> TestConstraintSystem<F> {
+    pub fn new() -> Self {
+        TestConstraintSystem {
+            inputs: vec![(F::ONE, "ONE".into())],
+            aux: vec![],
+            constraints: vec![],
+            current_namespace: vec![],
+        }
+    }
+
+    fn compute_path(&self, name: &str) -> String {
+        assert!(!name.contains('/'), "'/' is not allowed in names: {}", name);
+
+        let mut path = self.current_namespace.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(name);
+        path
+    }
+
+    fn eval_lc(&self, lc: &LinearCombination<F>) -> F {
+        lc.as_ref().iter().fold(F::ZERO, |acc, (var, coeff)| {
+            let value = match var.get_unchecked() {
+                Index::Input(i) => self.inputs[i].0,
+                Index::Aux(i) => self.aux[i].0,
+            };
+            acc + value * coeff
+        })
+    }
+
+    // Path of the first constraint that doesn't hold, if any
+    pub fn which_is_unsatisfied(&self) -> Option<&str> {
+        self.constraints
+            .iter()
+            .find(|(a, b, c, _)| self.eval_lc(a) * self.eval_lc(b) != self.eval_lc(c))
+            .map(|(_, _, _, path)| path.as_str())
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.which_is_unsatisfied().is_none()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    // Number of public inputs, not counting ONE
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len() - 1
+    }
+
+    // Number of private variables
+    pub fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
+
+    // Checks the public inputs (without ONE) against `expected`
+    pub fn verify(&self, expected: &[F]) -> bool {
+        self.inputs.len() == expected.len() + 1
+            && self.inputs[1..]
+                .iter()
+                .zip(expected)
+                .all(|((value, _), e)| value == e)
+    }
+
+    // Assignment of the variable allocated at `path`
+    pub fn get(&self, path: &str) -> F {
+        self.inputs
+            .iter()
+            .chain(self.aux.iter())
+            .find(|(_, p)| p == path)
+            .map(|(value, _)| *value)
+            .unwrap_or_else(|| panic!("no variable at path {}", path))
+    }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for TestConstraintSystem<F> {
+    type Root = Self;
+
+    fn alloc<V, A, AR>(&mut self, annotation: A, f: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.aux.len();
+        let path = self.compute_path(&annotation().into());
+        self.aux.push((f()?, path));
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<V, A, AR>(&mut self, annotation: A, f: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.inputs.len();
+        let path = self.compute_path(&annotation().into());
+        self.inputs.push((f()?, path));
+
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LB: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LC: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+    {
+        let path = self.compute_path(&annotation().into());
+        self.constraints.push((
+            a(LinearCombination::zero()),
+            b(LinearCombination::zero()),
+            c(LinearCombination::zero()),
+            path,
+        ));
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let name = name_fn().into();
+        self.current_namespace.push(name);
+    }
+
+    fn pop_namespace(&mut self) {
+        assert!(self.current_namespace.pop().is_some());
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar as Fr;
+
+    #[test]
+    fn reports_namespaced_path_of_first_failure() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::from(3))).unwrap();
+        let b = cs.alloc_input(|| "b", || Ok(Fr::from(9))).unwrap();
+
+        cs.enforce(|| "a * a = b", |lc| lc + a, |lc| lc + a, |lc| lc + b);
+        assert!(cs.is_satisfied());
+
+        {
+            let mut ns = cs.namespace(|| "outer");
+            let one = TestConstraintSystem::<Fr>::one();
+            ns.enforce(|| "a = b", |lc| lc + a, |lc| lc + one, |lc| lc + b);
+        }
+
+        assert_eq!(cs.which_is_unsatisfied(), Some("outer/a = b"));
+        assert_eq!(cs.num_constraints(), 2);
+        assert_eq!(cs.num_inputs(), 1);
+        assert!(cs.verify(&[Fr::from(9)]));
+        assert_eq!(cs.get("a"), Fr::from(3));
+    }
+}
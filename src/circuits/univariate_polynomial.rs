@@ -0,0 +1,149 @@
+use super::common::*;
+use bellman::LinearCombination;
+
+// Circuit: p(x) = c_0 + c_1 x + ... + c_d x^d = z, evaluated with Horner's rule.
+// x is private and z is public. The coefficients are either private or public
+// inputs; public coefficients follow z in the public input order.
+#[derive(Clone)]
+pub struct UnivariatePolynomialCircuit<F: PrimeField> {
+    pub degree: usize,
+    pub public_coefficients: bool,
+    pub coefficients: Option<Vec<F>>, // c_0 first, degree + 1 entries
+    pub x: Option<F>,
+    pub z: Option<F>,
+}
+
+// Native evaluation, used to compute the expected z
+pub fn evaluate<F: PrimeField>(coefficients: &[F], x: F) -> F {
+    coefficients
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, c| acc * x + c)
+}
+
+impl<F: PrimeField> Circuit<F> for UnivariatePolynomialCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let x = cs.alloc(|| "x", || self.x.grab())?;
+        let z = cs.alloc_input(|| "z", || self.z.grab())?;
+
+        let coefficient = |i: usize| {
+            self.coefficients
+                .as_ref()
+                .and_then(|c| c.get(i))
+                .copied()
+        };
+
+        let mut coefficient_vars = Vec::with_capacity(self.degree + 1);
+        for i in 0..=self.degree {
+            let annotation = || format!("c_{}", i);
+            coefficient_vars.push(if self.public_coefficients {
+                cs.alloc_input(annotation, || coefficient(i).grab())?
+            } else {
+                cs.alloc(annotation, || coefficient(i).grab())?
+            });
+        }
+
+        // Horner's rule: acc = c_d, then acc = acc * x + c_i for i = d - 1, ..., 0.
+        // Only acc * x needs a constraint; adding c_i stays a linear combination.
+        let mut acc = LinearCombination::zero() + coefficient_vars[self.degree];
+        let mut acc_value = coefficient(self.degree);
+
+        for i in (0..self.degree).rev() {
+            let product_value = match (acc_value, self.x) {
+                (Some(a), Some(x)) => Some(a * x),
+                _ => None,
+            };
+            let product = cs.alloc(|| format!("acc_{} * x", i), || product_value.grab())?;
+
+            cs.enforce(
+                || format!("horner step {}", i),
+                |_| acc,
+                |lc| lc + x,
+                |lc| lc + product,
+            );
+
+            acc = LinearCombination::zero() + product + coefficient_vars[i];
+            acc_value = match (product_value, coefficient(i)) {
+                (Some(p), Some(c)) => Some(p + c),
+                _ => None,
+            };
+        }
+
+        // Enforce p(x) = z
+        cs.enforce(
+            || "result constraint",
+            |_| acc,
+            |lc| lc + CS::one(),
+            |lc| lc + z,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(
+        coefficients: &[u64],
+        x: u64,
+        z: Fr,
+        public_coefficients: bool,
+    ) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        UnivariatePolynomialCircuit {
+            degree: coefficients.len() - 1,
+            public_coefficients,
+            coefficients: Some(coefficients.iter().map(|&c| Fr::from(c)).collect()),
+            x: Some(Fr::from(x)),
+            z: Some(z),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn valid_witness_is_satisfied() {
+        // 5 + 3x + 2x^2 + x^3 at x = 4 is 5 + 12 + 32 + 64
+        let cs = synthesize(&[5, 3, 2, 1], 4, Fr::from(113), false);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 4);
+        assert!(cs.verify(&[Fr::from(113)]));
+    }
+
+    #[test]
+    fn constant_polynomial_is_satisfied() {
+        let cs = synthesize(&[42], 7, Fr::from(42), false);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+    }
+
+    #[test]
+    fn high_degree_matches_native_evaluation() {
+        let coefficients: Vec<u64> = (1..=65).collect();
+        let expected = evaluate(
+            &coefficients.iter().map(|&c| Fr::from(c)).collect::<Vec<_>>(),
+            Fr::from(3),
+        );
+        let cs = synthesize(&coefficients, 3, expected, false);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 65);
+    }
+
+    #[test]
+    fn public_coefficients_are_inputs() {
+        let cs = synthesize(&[5, 3, 2, 1], 4, Fr::from(113), true);
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[113, 5, 3, 2, 1].map(Fr::from)));
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let cs = synthesize(&[5, 3, 2, 1], 4, Fr::from(114), false);
+        assert_eq!(cs.which_is_unsatisfied(), Some("result constraint"));
+    }
+}
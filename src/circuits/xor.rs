@@ -1,6 +1,8 @@
+use super::boolean::{AllocatedBit, Boolean};
 use super::common::*;
 
 // Circuit: a XOR b = result
+#[derive(Clone)]
 pub struct XorCircuit<F: PrimeField> {
     pub a: Option<F>,
     pub b: Option<F>,
@@ -9,31 +11,72 @@ pub struct XorCircuit<F: PrimeField> {
 
 impl<F: PrimeField> Circuit<F> for XorCircuit<F> {
     fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-        let a = cs.alloc(|| "a", || self.a.grab())?;
-        let b = cs.alloc(|| "b", || self.b.grab())?;
+        // Both operands must be bits, otherwise a + b - 2ab can be any value
+        let a = AllocatedBit::alloc_field(cs.namespace(|| "a"), self.a)?;
+        let b = AllocatedBit::alloc_field(cs.namespace(|| "b"), self.b)?;
         let result = cs.alloc_input(|| "result", || self.result.grab())?;
 
-        let two_ab = cs.alloc(
-            || "2 * a * b",
-            || Ok(F::from(2) * self.a.grab()? * self.b.grab()?),
-        )?;
-
-        // Enforce 2 * a * b = result
-        cs.enforce(
-            || "2 * a * b = result",
-            |lc| lc + (F::from(2), a),
-            |lc| lc + b,
-            |lc| lc + two_ab,
-        );
+        let xor = Boolean::xor(cs.namespace(|| "a xor b"), &a.into(), &b.into())?;
 
-        // XOR constraint: a + b - 2 * a * b = result
-        cs.enforce(
-            || "xor constraint",
-            |lc| lc + a + b - two_ab,
-            |lc| lc + CS::one(),
-            |lc| lc + result,
-        );
+        // XOR constraint: (a xor b) = result
+        Boolean::enforce_equal(cs.namespace(|| "xor constraint"), &xor, result);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+
+    fn synthesize(a: u64, b: u64, result: u64) -> TestConstraintSystem<Fr> {
+        let mut cs = TestConstraintSystem::new();
+        XorCircuit {
+            a: Some(Fr::from(a)),
+            b: Some(Fr::from(b)),
+            result: Some(Fr::from(result)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        cs
+    }
+
+    #[test]
+    fn truth_table_is_satisfied() {
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let cs = synthesize(a, b, a ^ b);
+            assert!(cs.is_satisfied(), "{} xor {}", a, b);
+        }
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let cs = synthesize(1, 1, 1);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("xor constraint/equality constraint")
+        );
+    }
+
+    #[test]
+    fn non_boolean_witness_is_rejected() {
+        // a + b - 2ab = 2 + 5 - 20, which the unconstrained circuit accepted
+        let mut cs = TestConstraintSystem::new();
+        XorCircuit {
+            a: Some(Fr::from(2)),
+            b: Some(Fr::from(5)),
+            result: Some(-Fr::from(13)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("a/boolean constraint"));
+    }
+
+    #[test]
+    fn non_boolean_second_operand_is_rejected() {
+        let cs = synthesize(0, 2, 2);
+        assert_eq!(cs.which_is_unsatisfied(), Some("b/boolean constraint"));
+    }
+}
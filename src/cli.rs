@@ -0,0 +1,928 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bellman::groth16::{prepare_verifying_key, verify_proof};
+use bls12_381::Scalar as Fr;
+use ff::PrimeField;
+
+use crate::analysis::{print_report, read_results};
+use crate::bellman_utils::*;
+use crate::circuits::fixed::{Fixed, FixedConfig};
+use crate::circuits::merkle::capacity;
+use crate::circuits::mlp::{parse_values, Mlp};
+use crate::circuits::sudoku::Grid;
+use crate::benchmark::{
+    print_batch_summary, print_summary, run_aggregation_benchmark, run_batch_benchmark,
+    run_benchmark, summarize, write_aggregation_results, write_batch_results, write_summary,
+    Mode,
+};
+use crate::inputs::Inputs;
+use crate::param_store::ParamStore;
+use crate::run_metadata::RunMetadata;
+use crate::serialization::{PointEncoding, ProofEnvelope};
+
+pub const USAGE: &str = "\
+usage: zk_snarks_bellman <command> [options]
+
+commands:
+  setup <circuit> [--inputs FILE]              generate and store fresh parameters
+  prove <circuit> [--inputs FILE] [--out FILE]  write a proof (default <circuit>.proof)
+  verify <circuit> --proof FILE [--inputs FILE]
+                                                check a proof against the stored key and
+                                                print the public inputs it proves; with
+                                                --inputs, they also have to be the
+                                                `public = ...` values in the file (field
+                                                elements, decimal or 0x hex as printed)
+  bench [circuit...] [--iterations N] [--warmup N] [--out FILE] [--summary FILE]
+        [--mode sequential | --mode parallel [--jobs N] | --mode isolated [--threads N]]
+                                                prove and verify, appending raw samples to
+                                                the CSV and writing per-stage statistics
+                                                (default: all circuits, 100 iterations,
+                                                1 warm-up round, results.csv,
+                                                results_summary.csv and the run metadata
+                                                in results_runs.jsonl)
+                                                parallel runs up to --jobs proofs at once
+                                                (default: one per core); isolated runs one
+                                                at a time on --threads threads (default 1)
+  batch [--sizes N,N,...] [--iterations N] [--out FILE]
+                                                compare batch verification of Fibonacci
+                                                proofs with one-by-one verification
+                                                (default sizes 1,10,100, 10 iterations,
+                                                batch_results.csv)
+  aggregate [--sizes N,N,...] [--iterations N] [--out FILE]
+                                                aggregate Fibonacci proofs SnarkPack-style,
+                                                writing a row per aggregate to the CSV
+                                                (default sizes 2,8,32, which need to be
+                                                powers of two, 5 iterations,
+                                                aggregate_results.csv)
+  analyze [--input FILE]                        summarize a results CSV, fit prover time
+                                                against constraints and flag outliers
+                                                (default results.csv; the runs from before
+                                                the schema versions are in results_v0.csv)
+
+every command also takes --params DIR (default params)
+
+circuits: polynomial, univariate_poly, mat_mul, multivar_poly, division,
+          integer_division, xor, fibonacci, sudoku, sha256, poseidon, merkle,
+          range, mlp
+          (bench sha256 poseidon compares the two hashes on the same data sizes)
+          (mlp inputs: model = FILE, see circuits/mlp.rs for the format, or
+          sizes = 4, 8, 8, 2 and salt = N for a generated model;
+          input = 0.5, -1, ...)";
+
+pub const CIRCUITS: [&str; 14] = [
+    "polynomial",
+    "univariate_poly",
+    "mat_mul",
+    "multivar_poly",
+    "division",
+    "integer_division",
+    "xor",
+    "fibonacci",
+    "sudoku",
+    "sha256",
+    "poseidon",
+    "merkle",
+    "range",
+    "mlp",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Setup {
+        circuit: String,
+        inputs: Option<PathBuf>,
+    },
+    Prove {
+        circuit: String,
+        inputs: Option<PathBuf>,
+        out: PathBuf,
+    },
+    Verify {
+        circuit: String,
+        proof: PathBuf,
+        inputs: Option<PathBuf>,
+    },
+    Analyze {
+        input: PathBuf,
+    },
+    Batch {
+        sizes: Vec<usize>,
+        iterations: usize,
+        out: PathBuf,
+    },
+    Aggregate {
+        sizes: Vec<usize>,
+        iterations: usize,
+        out: PathBuf,
+    },
+    Bench {
+        circuits: Vec<String>,
+        iterations: usize,
+        out: PathBuf,
+        warmup: usize,
+        summary: PathBuf,
+        mode: Mode,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub params: PathBuf,
+}
+
+// `--flag value` pairs, flag without the dashes
+type FlagValues<'a> = Vec<(&'a str, &'a str)>;
+
+// Splits the arguments after the command into positionals and flags,
+// rejecting flags the command doesn't know
+fn split_args<'a>(
+    args: &'a [String],
+    flags: &[&str],
+) -> Result<(Vec<&'a str>, FlagValues<'a>), String> {
+    let mut positional = vec![];
+    let mut options = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(flag) if flags.contains(&flag) || flag == "params" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("--{} needs a value", flag))?;
+                options.push((flag, value.as_str()));
+            }
+            Some(flag) => return Err(format!("unknown option --{}", flag)),
+            None => positional.push(arg.as_str()),
+        }
+    }
+
+    Ok((positional, options))
+}
+
+fn option<'a>(options: &[(&str, &'a str)], flag: &str) -> Option<&'a str> {
+    options.iter().rev().find(|(f, _)| *f == flag).map(|(_, v)| *v)
+}
+
+fn count(options: &[(&str, &str)], flag: &str, default: usize) -> Result<usize, String> {
+    match option(options, flag) {
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("invalid count '{}' for --{}", n, flag)),
+        None => Ok(default),
+    }
+}
+
+// A comma-separated list of positive sizes
+fn sizes(options: &[(&str, &str)], default: &str) -> Result<Vec<usize>, String> {
+    option(options, "sizes")
+        .unwrap_or(default)
+        .split(',')
+        .map(|n| match n.trim().parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("invalid size '{}'", n)),
+        })
+        .collect()
+}
+
+// results.csv -> results_summary.csv, next to the raw samples
+fn summary_path(out: &Path) -> PathBuf {
+    let stem = out.file_stem().and_then(|s| s.to_str()).unwrap_or("results");
+    out.with_file_name(format!("{}_summary.csv", stem))
+}
+
+fn bench_mode(options: &[(&str, &str)]) -> Result<Mode, String> {
+    let mode = option(options, "mode").unwrap_or("sequential");
+    let unused = match mode {
+        "sequential" => ["jobs", "threads"].as_slice(),
+        "parallel" => &["threads"],
+        "isolated" => &["jobs"],
+        other => return Err(format!("unknown mode '{}'", other)),
+    };
+    if let Some(flag) = unused.iter().find(|f| option(options, f).is_some()) {
+        return Err(format!("--{} doesn't apply to {} mode", flag, mode));
+    }
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mode = match mode {
+        "sequential" => Mode::Sequential,
+        "parallel" => Mode::Parallel {
+            jobs: count(options, "jobs", cores)?,
+        },
+        _ => Mode::Isolated {
+            threads: count(options, "threads", 1)?,
+        },
+    };
+
+    match mode {
+        Mode::Parallel { jobs: 0 } | Mode::Isolated { threads: 0 } => {
+            Err("--jobs and --threads need to be at least 1".into())
+        }
+        mode => Ok(mode),
+    }
+}
+
+fn circuit_name(name: &str) -> Result<String, String> {
+    if CIRCUITS.contains(&name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!("unknown circuit '{}'", name))
+    }
+}
+
+fn single_circuit(positional: &[&str]) -> Result<String, String> {
+    match positional {
+        [name] => circuit_name(name),
+        [] => Err("missing circuit name".into()),
+        _ => Err("expected a single circuit name".into()),
+    }
+}
+
+// Parses the arguments after the program name
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let (command, rest) = args.split_first().ok_or("missing command")?;
+
+    let flags: &[&str] = match command.as_str() {
+        "setup" => &["inputs"],
+        "prove" => &["inputs", "out"],
+        "verify" => &["proof", "inputs"],
+        "bench" => &["iterations", "out", "warmup", "summary", "mode", "jobs", "threads"],
+        "analyze" => &["input"],
+        "batch" | "aggregate" => &["sizes", "iterations", "out"],
+        other => return Err(format!("unknown command '{}'", other)),
+    };
+    let (positional, options) = split_args(rest, flags)?;
+
+    let command = match command.as_str() {
+        "setup" => Command::Setup {
+            circuit: single_circuit(&positional)?,
+            inputs: option(&options, "inputs").map(PathBuf::from),
+        },
+        "prove" => {
+            let circuit = single_circuit(&positional)?;
+            Command::Prove {
+                out: option(&options, "out")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from(format!("{}.proof", circuit))),
+                circuit,
+                inputs: option(&options, "inputs").map(PathBuf::from),
+            }
+        }
+        "verify" => Command::Verify {
+            circuit: single_circuit(&positional)?,
+            proof: option(&options, "proof")
+                .map(PathBuf::from)
+                .ok_or("verify needs --proof FILE")?,
+            inputs: option(&options, "inputs").map(PathBuf::from),
+        },
+        "batch" => {
+            if let Some(extra) = positional.first() {
+                return Err(format!("unexpected argument '{}'", extra));
+            }
+            Command::Batch {
+                sizes: sizes(&options, "1,10,100")?,
+                iterations: count(&options, "iterations", 10)?,
+                out: PathBuf::from(option(&options, "out").unwrap_or("batch_results.csv")),
+            }
+        }
+        "aggregate" => {
+            if let Some(extra) = positional.first() {
+                return Err(format!("unexpected argument '{}'", extra));
+            }
+            let sizes = sizes(&options, "2,8,32")?;
+            if let Some(size) = sizes.iter().find(|n| **n < 2 || !n.is_power_of_two()) {
+                return Err(format!("can't aggregate {} proofs, need a power of two", size));
+            }
+            Command::Aggregate {
+                sizes,
+                iterations: count(&options, "iterations", 5)?,
+                out: PathBuf::from(option(&options, "out").unwrap_or("aggregate_results.csv")),
+            }
+        }
+        "analyze" => {
+            if let Some(extra) = positional.first() {
+                return Err(format!("unexpected argument '{}'", extra));
+            }
+            Command::Analyze {
+                input: PathBuf::from(option(&options, "input").unwrap_or("results.csv")),
+            }
+        }
+        _ => {
+            let out = PathBuf::from(option(&options, "out").unwrap_or("results.csv"));
+            Command::Bench {
+                circuits: if positional.is_empty() {
+                    CIRCUITS.iter().map(|c| c.to_string()).collect()
+                } else {
+                    positional
+                        .iter()
+                        .map(|c| circuit_name(c))
+                        .collect::<Result<_, _>>()?
+                },
+                iterations: count(&options, "iterations", 100)?,
+                warmup: count(&options, "warmup", 1)?,
+                summary: option(&options, "summary")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| summary_path(&out)),
+                out,
+                mode: bench_mode(&options)?,
+            }
+        }
+    };
+
+    Ok(Options {
+        command,
+        params: PathBuf::from(option(&options, "params").unwrap_or("params")),
+    })
+}
+
+// Builds the job for `circuit` from the inputs file, with the same defaults
+// as the benchmark, and evaluates `$body` with it bound to `$job`. Keys in
+// the file the circuit doesn't read are an error. The job types differ per
+// circuit, hence a macro rather than a closure.
+macro_rules! with_job {
+    ($circuit:expr, $inputs:expr, |$job:ident| $body:expr) => {{
+        let inputs: &Inputs = $inputs;
+        match $circuit {
+            "polynomial" => {
+                let $job = polynomial_job(inputs.get("x", 256)?, inputs.get("y", 729)?);
+                inputs.check_unused()?;
+                $body
+            }
+            "univariate_poly" => {
+                let $job = univariate_polynomial_job(
+                    &inputs.list("coefficients", &[1, 2, 3, 4])?,
+                    inputs.get("x", 3)?,
+                    inputs.get("public_coefficients", false)?,
+                );
+                inputs.check_unused()?;
+                $body
+            }
+            "mat_mul" => {
+                let m1 = inputs.matrix("m1", &[vec![123, 456], vec![789, 101]])?;
+                let m2 = inputs.matrix("m2", &[vec![112, 131], vec![415, 161]])?;
+                check_mat_mul(&m1, &m2)?;
+                let $job = matrix_multiplication_job(&m1, &m2);
+                inputs.check_unused()?;
+                $body
+            }
+            "multivar_poly" => {
+                let $job = multivar_polynomial_job([
+                    inputs.get("x", 222)?,
+                    inputs.get("y", 333)?,
+                    inputs.get("a", 444)?,
+                    inputs.get("b", 555)?,
+                    inputs.get("c", 666)?,
+                    inputs.get("d", 777)?,
+                ]);
+                inputs.check_unused()?;
+                $body
+            }
+            "division" => {
+                let $job = division_job(
+                    inputs.get("numerator", 4003859412)?,
+                    inputs.get("denominator", 45678)?,
+                );
+                inputs.check_unused()?;
+                $body
+            }
+            "integer_division" => {
+                let $job = integer_division_job(
+                    inputs.get("numerator", 4003859500)?,
+                    inputs.get("denominator", 45678)?,
+                    division_bits(inputs.get("num_bits", 32)?)?,
+                );
+                inputs.check_unused()?;
+                $body
+            }
+            "xor" => {
+                let $job = xor_job(inputs.get("a", 1)?, inputs.get("b", 0)?);
+                inputs.check_unused()?;
+                $body
+            }
+            "fibonacci" => {
+                let $job = fibonacci_job(
+                    inputs.get("n", 10)?,
+                    inputs.get("f0", 0)?,
+                    inputs.get("f1", 1)?,
+                );
+                inputs.check_unused()?;
+                $body
+            }
+            "sudoku" => {
+                let default = SUDOKU_PUZZLE.map(|row| row.map(u64::from).to_vec());
+                let $job = sudoku_job(&sudoku_grid(&inputs.matrix("puzzle", &default)?)?);
+                inputs.check_unused()?;
+                $body
+            }
+            "sha256" => {
+                // The preimage as text, or else `length` bytes 0, 1, 2, ...
+                let preimage: String = inputs.get("preimage", String::new())?;
+                let $job = if preimage.is_empty() {
+                    sha256_job(&sha256_preimage(inputs.get("length", 64)?))
+                } else {
+                    sha256_job(preimage.as_bytes())
+                };
+                inputs.check_unused()?;
+                $body
+            }
+            "poseidon" => {
+                let $job = poseidon_job(&inputs.list("preimage", &[1, 2, 3])?);
+                inputs.check_unused()?;
+                $body
+            }
+            "merkle" => {
+                let depth = inputs.get("depth", 8)?;
+                let leaves = inputs.list("leaves", &[1, 2, 3, 4, 5, 6, 7, 8])?;
+                let index = inputs.get("index", 0)?;
+                check_merkle(depth, leaves.len(), index)?;
+                let $job = merkle_job(depth, &leaves, index);
+                inputs.check_unused()?;
+                $body
+            }
+            "range" => {
+                let $job = range_job(
+                    inputs.get("value", 1000)?,
+                    inputs.get("lo", 0)?,
+                    inputs.get("hi", 65535)?,
+                    range_bits(inputs.get("num_bits", 32)?)?,
+                );
+                inputs.check_unused()?;
+                $body
+            }
+            "mlp" => {
+                let (model, input) = mlp_inputs(&inputs)?;
+                let $job = mlp_job(&model, &input);
+                inputs.check_unused()?;
+                $body
+            }
+            other => Err(format!("unknown circuit '{}'", other).into()),
+        }
+    }};
+}
+
+// Nine rows of nine digits, 0 for blanks
+fn sudoku_grid(rows: &[Vec<u64>]) -> Result<Grid<u8>, String> {
+    let mut grid = [[0; 9]; 9];
+    if rows.len() != 9 || rows.iter().any(|row| row.len() != 9) {
+        return Err("a sudoku puzzle needs 9 rows of 9 cells".into());
+    }
+    for (r, row) in rows.iter().enumerate() {
+        for (c, &d) in row.iter().enumerate() {
+            grid[r][c] = u8::try_from(d)
+                .ok()
+                .filter(|d| *d <= 9)
+                .ok_or_else(|| format!("invalid sudoku cell '{}'", d))?;
+        }
+    }
+    Ok(grid)
+}
+
+// The leaves have to fit in the tree and the proven leaf be one of them
+fn check_merkle(depth: usize, leaves: usize, index: usize) -> Result<(), String> {
+    if leaves > capacity(depth) {
+        return Err(format!("{} leaves don't fit in a tree of depth {}", leaves, depth));
+    }
+    if index >= leaves {
+        return Err(format!("leaf index {} out of range for {} leaves", index, leaves));
+    }
+    Ok(())
+}
+
+// m1 has to be rows x inner and m2 inner x cols
+fn check_mat_mul(m1: &[Vec<u64>], m2: &[Vec<u64>]) -> Result<(), String> {
+    let inner = m2.len();
+    if let Some(row) = m1.iter().find(|row| row.len() != inner) {
+        let message = format!("m1 needs {} columns for the rows of m2, not {}", inner, row.len());
+        return Err(message);
+    }
+    if m2.iter().any(|row| row.len() != m2[0].len()) {
+        return Err("the rows of m2 need to be the same length".into());
+    }
+    Ok(())
+}
+
+// The product of quotient and denominator, plus the remainder, must not wrap
+// around the modulus
+fn division_bits(num_bits: usize) -> Result<usize, String> {
+    let max = (Fr::CAPACITY as usize - 1) / 2;
+    if (1..=max).contains(&num_bits) {
+        Ok(num_bits)
+    } else {
+        Err(format!("integer division takes 1 to {} bits, not {}", max, num_bits))
+    }
+}
+
+// The values of a range proof are u128s
+fn range_bits(num_bits: usize) -> Result<usize, String> {
+    match num_bits {
+        1..=128 => Ok(num_bits),
+        _ => Err(format!("range proofs take 1 to 128 bits, not {}", num_bits)),
+    }
+}
+
+// The model from the `model` file, or else a generated one with layer
+// `sizes`, and the input values
+fn mlp_inputs(inputs: &Inputs) -> Result<(Mlp, Vec<Fixed>), Box<dyn Error>> {
+    let (total_bits, frac_bits) = (inputs.get("total_bits", 32)?, inputs.get("frac_bits", 16)?);
+    if total_bits > 64 || frac_bits >= total_bits {
+        let message = format!("can't use {} bits with {} fractional ones", total_bits, frac_bits);
+        return Err(message.into());
+    }
+    let config = FixedConfig::new(total_bits, frac_bits);
+
+    let path: String = inputs.get("model", String::new())?;
+    let model = if path.is_empty() {
+        let sizes = inputs.list("sizes", &[4, 8, 8, 2])?;
+        if sizes.len() < 2 || sizes.contains(&0) {
+            return Err("a model needs at least two nonzero layer sizes".into());
+        }
+        let sizes: Vec<usize> = sizes.iter().map(|&s| s as usize).collect();
+        mlp_model(config, &sizes, inputs.get("salt", MLP_SALT)?)?
+    } else {
+        Mlp::read(config, Path::new(&path))?
+    };
+
+    let width = model.sizes()[0];
+    let text: String = inputs.get("input", String::new())?;
+    let input = if text.is_empty() {
+        mlp_input(config, width)?
+    } else {
+        parse_values(config, &text)?
+    };
+    if input.len() != width {
+        return Err(format!("the model takes {} inputs, not {}", width, input.len()).into());
+    }
+    Ok((model, input))
+}
+
+// The `public` list of a verify inputs file
+fn public_values(inputs: &Inputs) -> Result<Vec<Fr>, String> {
+    let text: String = inputs.get("public", String::new())?;
+    if text.is_empty() {
+        return Err("expected the public inputs as `public = ...`".into());
+    }
+    text.split(',').map(|x| field_element(x.trim())).collect()
+}
+
+// A canonical field element, in decimal up to 2^128 or in hex with a 0x prefix
+fn field_element(text: &str) -> Result<Fr, String> {
+    let value = match text.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() && hex.len() <= 64 => {
+            // The repr is little-endian
+            let mut repr = [0u8; 32];
+            for (i, c) in hex.chars().rev().enumerate() {
+                let digit = c.to_digit(16).ok_or_else(|| format!("invalid hex '{}'", text))?;
+                repr[i / 2] |= (digit as u8) << (4 * (i % 2));
+            }
+            Option::from(Fr::from_repr(repr))
+        }
+        Some(_) => None,
+        None => text.parse().ok().map(Fr::from_u128),
+    };
+    value.ok_or_else(|| format!("invalid field element '{}'", text))
+}
+
+// Shapes are made of letters, digits and `_`, so a proof file can't point the
+// key lookup outside the parameter directory
+fn valid_shape(shape: &str) -> bool {
+    !shape.is_empty() && shape.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn read_inputs(path: &Option<PathBuf>) -> Result<Inputs, Box<dyn Error>> {
+    match path {
+        Some(path) => Inputs::read(path),
+        None => Ok(Inputs::default()),
+    }
+}
+
+pub fn run(options: Options) -> Result<(), Box<dyn Error>> {
+    let store = ParamStore::new(options.params);
+
+    match options.command {
+        Command::Setup { circuit, inputs } => {
+            with_job!(circuit.as_str(), &read_inputs(&inputs)?, |job| {
+                let key = ParamStore::key(job.name, &job.shape);
+                job.setup(&store)?;
+                println!("Stored parameters for {}", key);
+                Ok(())
+            })
+        }
+        Command::Prove {
+            circuit,
+            inputs,
+            out,
+        } => with_job!(circuit.as_str(), &read_inputs(&inputs)?, |job| {
+            let envelope = job.prove(&store)?;
+            fs::write(&out, envelope.to_bytes(PointEncoding::Compressed)?)?;
+            println!("Wrote proof for {} to {}", envelope.circuit_id, out.display());
+            Ok(())
+        }),
+        Command::Verify {
+            circuit,
+            proof,
+            inputs,
+        } => {
+            let envelope = ProofEnvelope::from_bytes(&fs::read(&proof)?)?;
+
+            // Otherwise the statement is whatever the proof file says it is. Only
+            // the public values are needed, the witness stays with the prover.
+            if let Some(inputs) = &inputs {
+                let values = Inputs::read(inputs)?;
+                let expected = public_values(&values)
+                    .map_err(|e| format!("{}: {}", inputs.display(), e))?;
+                values.check_unused()?;
+                if envelope.public_inputs != expected {
+                    let message = format!(
+                        "the public inputs in {} don't match the ones {} gives",
+                        proof.display(),
+                        inputs.display()
+                    );
+                    return Err(message.into());
+                }
+            }
+
+            // The circuit id is the parameter key, "<circuit>" or "<circuit>_<shape>"
+            let shape = match envelope.circuit_id.strip_prefix(circuit.as_str()) {
+                Some("") => "",
+                Some(rest) if rest.starts_with('_') => {
+                    let shape = &rest[1..];
+                    if !valid_shape(shape) {
+                        let message = format!(
+                            "{} has an invalid circuit id {:?}",
+                            proof.display(),
+                            envelope.circuit_id
+                        );
+                        return Err(message.into());
+                    }
+                    shape
+                }
+                _ => {
+                    return Err(format!(
+                        "{} holds a proof for {}, not {}",
+                        proof.display(),
+                        envelope.circuit_id,
+                        circuit
+                    )
+                    .into())
+                }
+            };
+
+            let vk = store.load_verifying_key(&circuit, shape)?;
+            let pvk = prepare_verifying_key(&vk);
+            verify_proof(&pvk, &envelope.proof, &envelope.public_inputs)
+                .map_err(|e| format!("proof for {} is invalid: {}", envelope.circuit_id, e))?;
+            println!("Proof for {} is valid", envelope.circuit_id);
+            println!(
+                "Public inputs ({}):",
+                match inputs {
+                    Some(inputs) => format!("as given by {}", inputs.display()),
+                    None => "as stored in the proof, pass --inputs to check them".into(),
+                }
+            );
+            for input in &envelope.public_inputs {
+                println!("  {:?}", input);
+            }
+            Ok(())
+        }
+        Command::Batch {
+            sizes,
+            iterations,
+            out,
+        } => {
+            let run = RunMetadata::collect();
+            run.record(&out)?;
+
+            let samples = run_batch_benchmark(&store, &sizes, iterations)?;
+            print_batch_summary(&samples);
+            write_batch_results(&out, &run.run_id, &samples)?;
+            println!("Wrote batch timings to {}", out.display());
+            Ok(())
+        }
+        Command::Aggregate {
+            sizes,
+            iterations,
+            out,
+        } => {
+            let run = RunMetadata::collect();
+            run.record(&out)?;
+
+            let samples = run_aggregation_benchmark(&store, &sizes, iterations)?;
+            write_aggregation_results(&out, &run.run_id, &samples)?;
+            println!("Wrote aggregate timings to {}", out.display());
+            Ok(())
+        }
+        Command::Analyze { input } => {
+            print_report(&read_results(&input)?);
+            Ok(())
+        }
+        Command::Bench {
+            circuits,
+            iterations,
+            out,
+            warmup,
+            summary,
+            mode,
+        } => {
+            // Before anything touches rayon, so the metadata sees the pinned pool
+            mode.configure()?;
+            let mut run = RunMetadata::collect();
+            run.mode = Some(mode.describe());
+
+            let harness = Harness::recording(store, out, run)?;
+            let run = &harness.run;
+            println!(
+                "Run {} ({} build, {} rayon threads, commit {})",
+                run.run_id,
+                run.build_profile,
+                run.rayon_threads,
+                run.git_commit.as_deref().unwrap_or("unknown")
+            );
+            let samples = run_benchmark(&harness, &circuits, warmup, iterations, mode)?;
+
+            let summaries = summarize(&samples);
+            print_summary(&summaries);
+            write_summary(&summary, &summaries)?;
+            println!("Wrote summary to {}", summary.display());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &str) -> Result<Options, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(
+            parse_str("prove fibonacci --inputs fib.txt --params keys").unwrap(),
+            Options {
+                command: Command::Prove {
+                    circuit: "fibonacci".into(),
+                    inputs: Some("fib.txt".into()),
+                    out: "fibonacci.proof".into(),
+                },
+                params: "keys".into(),
+            }
+        );
+        assert_eq!(
+            parse_str("bench xor mat_mul --iterations 5").unwrap().command,
+            Command::Bench {
+                circuits: vec!["xor".into(), "mat_mul".into()],
+                iterations: 5,
+                out: "results.csv".into(),
+                warmup: 1,
+                summary: "results_summary.csv".into(),
+                mode: Mode::Sequential,
+            }
+        );
+        match parse_str("bench --out runs/today.csv --warmup 0").unwrap().command {
+            Command::Bench {
+                circuits,
+                warmup,
+                summary,
+                ..
+            } => {
+                assert_eq!(circuits.len(), CIRCUITS.len());
+                assert_eq!(warmup, 0);
+                assert_eq!(summary, PathBuf::from("runs/today_summary.csv"));
+            }
+            command => panic!("unexpected {:?}", command),
+        }
+        assert_eq!(
+            parse_str("aggregate --sizes 4,16").unwrap().command,
+            Command::Aggregate {
+                sizes: vec![4, 16],
+                iterations: 5,
+                out: "aggregate_results.csv".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(parse_str("").is_err());
+        assert!(parse_str("frobnicate").is_err());
+        assert!(parse_str("setup").is_err());
+        assert!(parse_str("setup chess").is_err());
+        assert!(parse_str("verify xor").is_err());
+        assert!(parse_str("prove xor --proof x").is_err());
+        assert!(parse_str("bench --iterations").is_err());
+        assert!(parse_str("bench --iterations many").is_err());
+        assert!(parse_str("analyze results.csv").is_err());
+        assert!(parse_str("bench --mode turbo").is_err());
+        assert!(parse_str("batch --sizes 1,0").is_err());
+        assert!(parse_str("aggregate --sizes 2,6").is_err());
+        assert!(parse_str("aggregate --sizes 1").is_err());
+        assert!(parse_str("bench --mode parallel --threads 2").is_err());
+        assert!(parse_str("bench --mode isolated --threads 0").is_err());
+    }
+
+    #[test]
+    fn incompatible_matrices_are_errors() {
+        assert!(check_mat_mul(&[vec![1, 2], vec![3, 4]], &[vec![5], vec![6]]).is_ok());
+        let error = check_mat_mul(&[vec![1, 2, 3]], &[vec![5], vec![6]]).unwrap_err();
+        assert_eq!(error, "m1 needs 2 columns for the rows of m2, not 3");
+        assert!(check_mat_mul(&[vec![1, 2]], &[vec![5, 6], vec![7]]).is_err());
+    }
+
+    #[test]
+    fn bit_widths_are_checked() {
+        assert_eq!(division_bits(126), Ok(126));
+        assert!(division_bits(127).is_err());
+        assert!(division_bits(0).is_err());
+        assert_eq!(range_bits(128), Ok(128));
+        assert!(range_bits(129).is_err());
+    }
+
+    #[test]
+    fn field_elements_are_parsed() {
+        let modulus = Fr::MODULUS;
+        assert_eq!(field_element("12"), Ok(Fr::from(12)));
+        assert_eq!(field_element("0x0c"), Ok(Fr::from(12)));
+        assert_eq!(field_element(&format!("{:?}", -Fr::from(12))), Ok(-Fr::from(12)));
+        // The modulus itself and anything longer than 256 bits
+        assert!(field_element(modulus).is_err());
+        assert!(field_element(&format!("0x1{}", &modulus[2..])).is_err());
+        assert!(field_element("0x").is_err());
+        assert!(field_element("0xg").is_err());
+        assert!(field_element("-1").is_err());
+    }
+
+    #[test]
+    fn unusable_mlp_configurations_are_errors() {
+        let mlp = |text: &str| mlp_inputs(&Inputs::parse(text).unwrap()).map(|_| ());
+
+        assert!(mlp("total_bits = 32\nfrac_bits = 16").is_ok());
+        // Too wide for the dot products, and too narrow for the generated model
+        let error = mlp("total_bits = 64\nfrac_bits = 16").unwrap_err().to_string();
+        assert_eq!(error, "layer 1 has 4 inputs, too many to sum at 64 bits");
+        let error = mlp("total_bits = 2\nfrac_bits = 1").unwrap_err().to_string();
+        assert!(error.contains("doesn't fit 2 bits"), "{}", error);
+    }
+
+    #[test]
+    fn proofs_round_trip_through_files() {
+        let dir = std::env::temp_dir().join(format!("cli_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let inputs = dir.join("inputs.txt");
+        let proof = dir.join("xor.proof");
+        fs::write(&inputs, "a = 1\nb = 1\n").unwrap();
+
+        let run_str = |args: String| run(parse_str(&args).unwrap());
+        let params = dir.join("params");
+        let (params, inputs, proof) = (params.display(), inputs.display(), proof.display());
+
+        run_str(format!("prove xor --inputs {} --out {} --params {}", inputs, proof, params))
+            .unwrap();
+        run_str(format!("verify xor --proof {} --params {}", proof, params)).unwrap();
+
+        // The verifier only needs the public values, here 1 xor 1 = 0
+        let public = dir.join("public.txt");
+        fs::write(&public, "public = 0\n").unwrap();
+        let public = public.display();
+        run_str(format!("verify xor --proof {} --inputs {} --params {}", proof, public, params))
+            .unwrap();
+        assert!(run_str(format!("verify division --proof {} --params {}", proof, params)).is_err());
+
+        // A proof whose circuit id would lead out of the parameter directory
+        let mut envelope = ProofEnvelope::from_bytes(&fs::read(dir.join("xor.proof")).unwrap())
+            .unwrap();
+        envelope.circuit_id = "xor_../../xor".into();
+        let forged = dir.join("forged.proof");
+        fs::write(&forged, envelope.to_bytes(PointEncoding::Compressed).unwrap()).unwrap();
+        let forged = forged.display();
+        let error = run_str(format!("verify xor --proof {} --params {}", forged, params))
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid circuit id"), "{}", error);
+
+        // A misspelled key doesn't silently fall back to the default
+        let typo = dir.join("typo.txt");
+        fs::write(&typo, "a = 1
+c = 1
+").unwrap();
+        let error = run_str(format!("prove xor --inputs {} --params {}", typo.display(), params))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "unused input 'c'");
+
+        // A valid proof of 1 xor 1 = 0 doesn't verify the statement 1 xor 0 = 1
+        let other = dir.join("other.txt");
+        fs::write(&other, "public = 1\n").unwrap();
+        let other = other.display();
+        let error = run_str(format!(
+            "verify xor --proof {} --inputs {} --params {}",
+            proof, other, params
+        ))
+        .unwrap_err();
+        assert!(error.to_string().contains("don't match"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
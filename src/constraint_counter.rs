@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use blake2s_simd::State;
+use ff::PrimeField;
+
+// Shape of a synthesized circuit, as measured by `ConstraintCounter`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CircuitMetrics {
+    pub num_constraints: usize,
+    pub num_inputs: usize, // public inputs, not counting the constant ONE
+    pub num_aux: usize,    // private (auxiliary) variables
+    pub a_terms: usize,    // distinct variables with a non-zero coefficient, over all A
+    pub b_terms: usize,
+    pub c_terms: usize,
+}
+
+impl CircuitMetrics {
+    // Total number of linear-combination terms over A, B and C
+    pub fn density(&self) -> usize {
+        self.a_terms + self.b_terms + self.c_terms
+    }
+}
+
+// A constraint system that only counts what a circuit allocates and enforces.
+// Like the Groth16 key generator, it never evaluates witness closures, so it
+// works on circuits built with `None` values as well. Along the way it hashes
+// the constraints into a fingerprint of the R1CS, which changes with any
+// coefficient even when the counts stay the same.
+#[derive(Default)]
+pub struct ConstraintCounter {
+    metrics: CircuitMetrics,
+    hasher: State,
+}
+
+impl ConstraintCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn metrics(&self) -> CircuitMetrics {
+        self.metrics
+    }
+
+    // BLAKE2s of the counts and of every constraint's merged terms, as hex
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = self.hasher.clone();
+        let metrics = self.metrics;
+        for count in [metrics.num_constraints, metrics.num_inputs, metrics.num_aux] {
+            hasher.update(&(count as u64).to_le_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    // Number of terms, then each variable and coefficient in variable order
+    fn hash_terms<F: PrimeField>(&mut self, terms: &BTreeMap<(u8, usize), F>) {
+        self.hasher.update(&(terms.len() as u64).to_le_bytes());
+        for ((kind, index), coeff) in terms {
+            self.hasher.update(&[*kind]);
+            self.hasher.update(&(*index as u64).to_le_bytes());
+            self.hasher.update(coeff.to_repr().as_ref());
+        }
+    }
+}
+
+// Terms are merged per variable first, so `x + x` counts once and `x - x` not
+// at all, as in the matrices the prover ends up with
+fn merge_terms<F: PrimeField>(lc: &LinearCombination<F>) -> BTreeMap<(u8, usize), F> {
+    let mut merged = BTreeMap::new();
+    for (var, coeff) in lc.as_ref() {
+        let key = match var.get_unchecked() {
+            Index::Input(i) => (0, i),
+            Index::Aux(i) => (1, i),
+        };
+        *merged.entry(key).or_insert(F::ZERO) += coeff;
+    }
+
+    merged.retain(|_, coeff| !bool::from(coeff.is_zero()));
+    merged
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for ConstraintCounter {
+    type Root = Self;
+
+    fn alloc<V, A, AR>(&mut self, _: A, _: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.metrics.num_aux;
+        self.metrics.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<V, A, AR>(&mut self, _: A, _: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.metrics.num_inputs += 1;
+
+        // Input 0 is reserved for ONE
+        Ok(Variable::new_unchecked(Index::Input(self.metrics.num_inputs)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LB: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LC: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+    {
+        let a = merge_terms(&a(LinearCombination::zero()));
+        let b = merge_terms(&b(LinearCombination::zero()));
+        let c = merge_terms(&c(LinearCombination::zero()));
+        for terms in [&a, &b, &c] {
+            self.hash_terms(terms);
+        }
+
+        self.metrics.a_terms += a.len();
+        self.metrics.b_terms += b.len();
+        self.metrics.c_terms += c.len();
+        self.metrics.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Namespaces don't affect the shape of the circuit
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// Synthesizes `circuit` and returns its exact shape
+pub fn count_constraints<F: PrimeField, C: Circuit<F>>(
+    circuit: C,
+) -> Result<CircuitMetrics, SynthesisError> {
+    Ok(measure_circuit(circuit)?.0)
+}
+
+// Synthesizes `circuit` and returns its shape and R1CS fingerprint
+pub fn measure_circuit<F: PrimeField, C: Circuit<F>>(
+    circuit: C,
+) -> Result<(CircuitMetrics, String), SynthesisError> {
+    let mut cs = ConstraintCounter::new();
+    circuit.synthesize(&mut cs)?;
+    Ok((cs.metrics(), cs.fingerprint()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::fibonacci::FibonacciCircuit;
+    use crate::circuits::test_cs::TestConstraintSystem;
+    use bls12_381::Scalar as Fr;
+    use ff::Field;
+
+    #[test]
+    fn matches_the_test_constraint_system() {
+        let circuit = FibonacciCircuit {
+            f0: Some(Fr::from(0)),
+            f1: Some(Fr::from(1)),
+            fn_val: Some(Fr::from(55)),
+            n: 10,
+        };
+
+        let mut cs = TestConstraintSystem::new();
+        circuit.clone().synthesize(&mut cs).unwrap();
+        let metrics = count_constraints(circuit).unwrap();
+
+        assert_eq!(metrics.num_constraints, cs.num_constraints());
+        assert_eq!(metrics.num_inputs, cs.num_inputs());
+        assert_eq!(metrics.num_aux, cs.num_aux());
+        assert_eq!((metrics.num_constraints, metrics.num_inputs, metrics.num_aux), (10, 1, 11));
+        // 9 times (prev + current) * ONE = next, then ONE * f10 = fn_val
+        assert_eq!((metrics.a_terms, metrics.b_terms, metrics.c_terms), (19, 10, 10));
+    }
+
+    #[test]
+    fn fingerprints_follow_the_coefficients() {
+        let fingerprint = |a: LinearCombination<Fr>| {
+            let mut cs = ConstraintCounter::new();
+            let x = ConstraintSystem::<Fr>::alloc(&mut cs, || "x", || Ok(Fr::ONE)).unwrap();
+            cs.enforce(|| "c", |lc| lc + &a, |lc| lc + x, |lc| lc + x);
+            cs.fingerprint()
+        };
+        let x = Variable::new_unchecked(Index::Aux(0));
+        let scaled = |c: u64| LinearCombination::zero() + (Fr::from(c), x);
+
+        // The same constraint written two ways, then one with another coefficient
+        assert_eq!(fingerprint(scaled(2)), fingerprint(LinearCombination::zero() + x + x));
+        assert_ne!(fingerprint(scaled(2)), fingerprint(scaled(3)));
+    }
+
+    #[test]
+    fn repeated_variables_are_merged() {
+        let mut cs = ConstraintCounter::new();
+        let x = ConstraintSystem::<Fr>::alloc(&mut cs, || "x", || Ok(Fr::ONE)).unwrap();
+        let y = ConstraintSystem::<Fr>::alloc(&mut cs, || "y", || Ok(Fr::ONE)).unwrap();
+        let one = <ConstraintCounter as ConstraintSystem<Fr>>::one();
+
+        // A = x + y + x, B = x - x + ONE, C = y
+        cs.enforce(
+            || "repeated",
+            |lc| lc + x + y + x,
+            |lc: LinearCombination<Fr>| lc + x - x + one,
+            |lc| lc + y,
+        );
+
+        let metrics = cs.metrics();
+        assert_eq!((metrics.a_terms, metrics.b_terms, metrics.c_terms), (2, 1, 1));
+        assert_eq!(metrics.density(), 4);
+    }
+}
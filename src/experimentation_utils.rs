@@ -1,54 +1,157 @@
 use std::error::Error;
-use std::fs::OpenOptions;
-use std::io::{Seek, SeekFrom, Write};
-
-use bellman::groth16::Proof;
-use bls12_381::Bls12;
-
-pub fn write_to_csv(
-    file_name: &str,
-    proof_type: &str,
-    proof_time: f64,
-    r_proof_size: i32,
-    s_proof_size: i32,
-    verification_time: f64,
-    num_constraints: i32,
-) -> Result<(), Box<dyn Error>> {
-    let file_exists = std::path::Path::new(file_name).exists();
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::constraint_counter::CircuitMetrics;
+
+// Every header `write_to_csv` has used, oldest first; the index is the schema
+// version. New columns are only ever appended, so older readers still find
+// theirs in place.
+pub const CSV_HEADERS: [&str; 4] = [
+    "proof_type,proof_time,r_proof_size,s_proof_size,verification_time,num_constraints",
+    "proof_type,proof_time,r_proof_size,s_proof_size,verification_time,num_constraints,\
+     num_inputs,num_aux,density",
+    "proof_type,proof_time,r_proof_size,s_proof_size,verification_time,num_constraints,\
+     num_inputs,num_aux,density,setup_time,params_generated,vk_prep_time,synthesis_time,\
+     params_size",
+    "proof_type,proof_time,r_proof_size,s_proof_size,verification_time,num_constraints,\
+     num_inputs,num_aux,density,setup_time,params_generated,vk_prep_time,synthesis_time,\
+     params_size,run_id",
+];
+
+pub const CSV_VERSION: usize = CSV_HEADERS.len() - 1;
+
+// One proof run of a circuit. Times are wall-clock seconds. `setup_time` is
+// the parameter generation time, or only the load time when `params_generated`
+// is false. `proof_time` includes witness synthesis, which is also timed on
+// its own as `synthesis_time`.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub proof_type: String,
+    pub setup_time: f64,
+    pub params_generated: bool,
+    pub vk_prep_time: f64,
+    pub synthesis_time: f64,
+    pub proof_time: f64,
+    pub verification_time: f64,
+    pub r_proof_size: usize, // in-memory size of the proof struct
+    pub s_proof_size: usize, // compressed serialized size
+    pub params_size: u64,    // size of the stored parameter file
+    pub metrics: CircuitMetrics,
+}
+
+// Fails unless `file_name` starts with the current header, so rows of different
+// schemas never end up in the same file
+pub fn check_csv_header(file_name: &Path) -> Result<(), Box<dyn Error>> {
+    let mut header = String::new();
+    BufReader::new(File::open(file_name)?).read_line(&mut header)?;
+    let header = header.trim_end();
+
+    match CSV_HEADERS.iter().position(|h| *h == header) {
+        Some(CSV_VERSION) => Ok(()),
+        Some(version) => Err(format!(
+            "{} uses results schema v{}, expected v{}; write to a new file instead",
+            file_name.display(),
+            version,
+            CSV_VERSION
+        )
+        .into()),
+        None => Err(format!("{} doesn't have a known results header", file_name.display()).into()),
+    }
+}
+
+// `run_id` links the row to its entry in the run metadata sidecar
+pub fn write_to_csv(file_name: &Path, run_id: &str, sample: &Sample) -> Result<(), Box<dyn Error>> {
+    let file_exists = file_name.exists();
+    if file_exists {
+        check_csv_header(file_name)?;
+    }
+
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
         .open(file_name)?;
 
     if !file_exists {
-        writeln!(
-            file,
-            "proof_type,proof_time,r_proof_size,s_proof_size,verification_time,num_constraints"
-        )?;
-    } else {
-        // Move the cursor to the end of the file to append data
-        file.seek(SeekFrom::End(0))?;
+        writeln!(file, "{}", CSV_HEADERS[CSV_VERSION])?;
     }
 
+    let metrics = &sample.metrics;
     writeln!(
         file,
-        "{proof_type},{proof_time},{r_proof_size},{s_proof_size},{verification_time},{num_constraints}"
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        sample.proof_type,
+        sample.proof_time,
+        sample.r_proof_size,
+        sample.s_proof_size,
+        sample.verification_time,
+        metrics.num_constraints,
+        metrics.num_inputs,
+        metrics.num_aux,
+        metrics.density(),
+        sample.setup_time,
+        sample.params_generated,
+        sample.vk_prep_time,
+        sample.synthesis_time,
+        sample.params_size,
+        run_id
     )?;
     Ok(())
 }
 
-pub fn proof_to_bytes(proof: &Proof<Bls12>) -> Vec<u8> {
-    let mut bytes = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn sample() -> Sample {
+        Sample {
+            proof_type: "xor".into(),
+            setup_time: 0.5,
+            params_generated: true,
+            vk_prep_time: 0.01,
+            synthesis_time: 0.001,
+            proof_time: 0.04,
+            verification_time: 0.005,
+            r_proof_size: 408,
+            s_proof_size: 192,
+            params_size: 2000,
+            metrics: CircuitMetrics::default(),
+        }
+    }
+
+    #[test]
+    fn rows_are_appended_under_the_current_header() {
+        let path = std::env::temp_dir().join(format!("results_new_{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        write_to_csv(&path, "run", &sample()).unwrap();
+        write_to_csv(&path, "run", &sample()).unwrap();
 
-    // Get compressed representations
-    let a_bytes = proof.a.to_compressed();
-    let b_bytes = proof.b.to_compressed();
-    let c_bytes = proof.c.to_compressed();
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADERS[CSV_VERSION]);
+        assert_eq!(lines[1].split(',').count(), lines[0].split(',').count());
 
-    // Concatenate all bytes
-    bytes.extend_from_slice(&a_bytes);
-    bytes.extend_from_slice(&b_bytes);
-    bytes.extend_from_slice(&c_bytes);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn older_schemas_are_not_mixed_in() {
+        let path = std::env::temp_dir().join(format!("results_old_{}.csv", std::process::id()));
+        fs::write(&path, format!("{}\nxor,0.04,408,192,0.005,4\n", CSV_HEADERS[0])).unwrap();
+
+        let error = write_to_csv(&path, "run", &sample()).unwrap_err().to_string();
+        assert!(error.contains("schema v0"), "{}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
 
-    bytes
+        fs::remove_file(&path).unwrap();
+
+        // The runs recorded before the header was versioned stay as they were
+        let legacy = Path::new(env!("CARGO_MANIFEST_DIR")).join("results_v0.csv");
+        let error = check_csv_header(&legacy).unwrap_err().to_string();
+        assert!(error.contains("schema v0"), "{}", error);
+    }
 }
@@ -0,0 +1,146 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Circuit inputs read from a plain `key = value` file, one entry per line.
+// `#` starts a comment. Lists are comma separated and matrices separate their
+// rows with `;`, e.g.
+//
+//     # 2x2 matrices
+//     m1 = 123, 456; 789, 101
+//     m2 = 112, 131; 415, 161
+//
+// Keys that are missing fall back to the default the caller passes in. The
+// keys that were looked up are remembered, so `check_unused` can catch typos
+// like `nm_bits` that would otherwise leave the default in place.
+#[derive(Default)]
+pub struct Inputs {
+    values: HashMap<String, String>,
+    used: RefCell<HashSet<String>>,
+}
+
+impl Inputs {
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Inputs::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", n + 1))?;
+            let key = key.trim();
+            if values.insert(key.to_string(), value.trim().to_string()).is_some() {
+                return Err(format!("line {}: duplicate key '{}'", n + 1, key));
+            }
+        }
+
+        Ok(Inputs {
+            values,
+            used: RefCell::default(),
+        })
+    }
+
+    fn value(&self, key: &str) -> Option<&String> {
+        self.used.borrow_mut().insert(key.to_string());
+        self.values.get(key)
+    }
+
+    // Fails on the first key, in alphabetical order, that nothing looked up
+    pub fn check_unused(&self) -> Result<(), String> {
+        let used = self.used.borrow();
+        let mut unused: Vec<&String> = self.values.keys().filter(|k| !used.contains(*k)).collect();
+        unused.sort();
+        match unused.first() {
+            Some(key) => Err(format!("unused input '{}'", key)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.value(key) {
+            Some(value) => parse_value(key, value),
+            None => Ok(default),
+        }
+    }
+
+    pub fn list(&self, key: &str, default: &[u64]) -> Result<Vec<u64>, String> {
+        match self.value(key) {
+            Some(value) => parse_list(key, value),
+            None => Ok(default.to_vec()),
+        }
+    }
+
+    pub fn matrix(&self, key: &str, default: &[Vec<u64>]) -> Result<Vec<Vec<u64>>, String> {
+        match self.value(key) {
+            Some(value) => value.split(';').map(|row| parse_list(key, row)).collect(),
+            None => Ok(default.to_vec()),
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value.trim(), key))
+}
+
+fn parse_list(key: &str, value: &str) -> Result<Vec<u64>, String> {
+    value.split(',').map(|x| parse_value(key, x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_lists_and_matrices_are_parsed() {
+        let inputs = Inputs::parse(
+            "# comment\n\
+             n = 10\n\
+             public = true  # trailing comment\n\
+             coefficients = 1, 2,3\n\
+             m = 1, 2; 3, 4\n",
+        )
+        .unwrap();
+
+        assert_eq!(inputs.get("n", 0usize), Ok(10));
+        assert_eq!(inputs.get("public", false), Ok(true));
+        assert_eq!(inputs.list("coefficients", &[]), Ok(vec![1, 2, 3]));
+        assert_eq!(inputs.matrix("m", &[]), Ok(vec![vec![1, 2], vec![3, 4]]));
+        assert_eq!(inputs.get("missing", 7u64), Ok(7));
+        assert_eq!(inputs.check_unused(), Ok(()));
+    }
+
+    #[test]
+    fn unused_keys_are_reported() {
+        let inputs = Inputs::parse("n = 10
+nm_bits = 64
+zeta = 1").unwrap();
+        inputs.get("n", 0u64).unwrap();
+        inputs.get("num_bits", 32u64).unwrap();
+        assert_eq!(inputs.check_unused(), Err("unused input 'nm_bits'".into()));
+    }
+
+    #[test]
+    fn malformed_inputs_are_rejected() {
+        assert!(Inputs::parse("n 10").is_err());
+        assert!(Inputs::parse("n = 1\nn = 2").is_err());
+
+        let inputs = Inputs::parse("n = ten\nlist = 1,,2").unwrap();
+        assert!(inputs.get("n", 0u64).is_err());
+        assert!(inputs.list("list", &[]).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+pub mod aggregation;
+pub mod analysis;
+pub mod bellman_utils;
+pub mod benchmark;
+pub mod circuits;
+pub mod cli;
+pub mod constraint_counter;
+pub mod experimentation_utils;
+pub mod inputs;
+pub mod param_store;
+pub mod run_metadata;
+pub mod serialization;
+pub mod witness;
@@ -3,39 +3,25 @@ extern crate bincode;
 extern crate ff;
 extern crate rand;
 
-mod bellman_utils;
-mod circuits;
-mod experimentation_utils;
+use std::process::exit;
 
-use bellman_utils::{
-    verify_division, verify_fibonacci, verify_matrix_multiplication, verify_multivar_polynomial,
-    verify_polynomial, verify_xor,
-};
+use zk_snarks_bellman::cli;
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
-    // Uncomment the function you want to run
 
-    let iterations = 100;
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    println!("Running tests");
-
-    let fibo_tests = vec![
-        (10, 55),
-        (15, 610),
-        (20, 6765),
-        (25, 75025),
-    ];
-
-    for _ in 0..iterations {
-        verify_polynomial();
-        verify_matrix_multiplication();
-        verify_multivar_polynomial();
-        verify_division();
-        verify_xor();
-
-        for (n, expected) in &fibo_tests {
-            verify_fibonacci(*n, *expected);
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            exit(2);
         }
+    };
+
+    if let Err(e) = cli::run(options) {
+        eprintln!("error: {}", e);
+        exit(1);
     }
 }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use bellman::groth16::{generate_random_parameters, Parameters, VerifyingKey};
+use bellman::Circuit;
+use bls12_381::{Bls12, Scalar as Fr};
+use rand::thread_rng;
+
+use crate::constraint_counter::{measure_circuit, CircuitMetrics};
+
+// Caches Groth16 parameters on disk, so every run of a circuit proves against
+// the same key instead of a fresh one. Files are named after the circuit and
+// its shape (e.g. "fibonacci_n10"): `<key>.params` holds the full proving
+// parameters, `<key>.vk` just the verifying key and `<key>.r1cs` the
+// fingerprint of the constraint system they were generated for.
+#[derive(Clone)]
+pub struct ParamStore {
+    dir: PathBuf,
+    // Compare loaded parameters against the circuit's measured shape and
+    // fingerprint, which catches files left over from an older version of the
+    // circuit, even one that only changed a coefficient
+    pub check_shape: bool,
+}
+
+impl Default for ParamStore {
+    fn default() -> Self {
+        ParamStore::new("params")
+    }
+}
+
+impl ParamStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ParamStore {
+            dir: dir.into(),
+            check_shape: true,
+        }
+    }
+
+    // File stem for a circuit and shape, also used as the circuit id of proofs
+    pub fn key(name: &str, shape: &str) -> String {
+        if shape.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}_{}", name, shape)
+        }
+    }
+
+    pub fn params_path(&self, name: &str, shape: &str) -> PathBuf {
+        self.dir.join(format!("{}.params", Self::key(name, shape)))
+    }
+
+    pub fn vk_path(&self, name: &str, shape: &str) -> PathBuf {
+        self.dir.join(format!("{}.vk", Self::key(name, shape)))
+    }
+
+    pub fn fingerprint_path(&self, name: &str, shape: &str) -> PathBuf {
+        self.dir.join(format!("{}.r1cs", Self::key(name, shape)))
+    }
+
+    // Loads the parameters for `name`/`shape`, generating and storing them from
+    // the `blank` circuit (no witness needed) if they don't exist yet
+    pub fn load_or_generate<C: Circuit<Fr> + Clone>(
+        &self,
+        name: &str,
+        shape: &str,
+        blank: C,
+    ) -> Result<Parameters<Bls12>, Box<dyn Error>> {
+        Ok(self.load_or_generate_once(name, shape, blank)?.0)
+    }
+
+    // Like `load_or_generate`, and also tells whether this call generated the
+    // parameters. Concurrent calls for a missing key wait for the first one to
+    // generate it and then load its parameters, so every proof of a shape uses
+    // the same key and only one call reports generating it.
+    pub fn load_or_generate_once<C: Circuit<Fr> + Clone>(
+        &self,
+        name: &str,
+        shape: &str,
+        blank: C,
+    ) -> Result<(Parameters<Bls12>, bool), Box<dyn Error>> {
+        let path = self.params_path(name, shape);
+
+        // Files only appear once fully written, so loading needs no lock
+        if !path.exists() {
+            let lock = key_lock(&path);
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            if !path.exists() {
+                return Ok((self.generate(name, shape, blank)?, true));
+            }
+        }
+
+        let params = read_params(&path)?;
+        if self.check_shape {
+            let (metrics, fingerprint) = measure_circuit(blank)?;
+            check_params(&params, &metrics)
+                .and_then(|()| self.check_fingerprint(name, shape, &fingerprint))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok((params, false))
+    }
+
+    fn check_fingerprint(&self, name: &str, shape: &str, expected: &str) -> Result<(), String> {
+        let stored = fs::read_to_string(self.fingerprint_path(name, shape)).unwrap_or_default();
+        if stored.trim() != expected {
+            return Err("parameters were generated for another version of the circuit; \
+                        run `setup` or delete them to regenerate"
+                .into());
+        }
+        Ok(())
+    }
+
+    // Generates fresh parameters and overwrites any stored ones. The
+    // fingerprint goes first, so a reader that finds the parameters also
+    // finds the fingerprint that belongs to them.
+    pub fn generate<C: Circuit<Fr> + Clone>(
+        &self,
+        name: &str,
+        shape: &str,
+        blank: C,
+    ) -> Result<Parameters<Bls12>, Box<dyn Error>> {
+        let (_, fingerprint) = measure_circuit(blank.clone())?;
+        let params = generate_random_parameters::<Bls12, _, _>(blank, &mut thread_rng())?;
+
+        fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.fingerprint_path(name, shape), |w| {
+            writeln!(w, "{}", fingerprint)
+        })?;
+        write_atomically(&self.params_path(name, shape), |w| params.write(w))?;
+        write_atomically(&self.vk_path(name, shape), |w| params.vk.write(w))?;
+
+        Ok(params)
+    }
+
+    pub fn load_verifying_key(
+        &self,
+        name: &str,
+        shape: &str,
+    ) -> Result<VerifyingKey<Bls12>, Box<dyn Error>> {
+        let file = File::open(self.vk_path(name, shape))?;
+        Ok(VerifyingKey::read(BufReader::new(file))?)
+    }
+}
+
+// One lock per parameter file, shared by every store in the process
+fn key_lock(path: &Path) -> Arc<Mutex<()>> {
+    static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+    let mut locks = LOCKS.get_or_init(Default::default).lock().unwrap();
+    locks.entry(path.to_path_buf()).or_default().clone()
+}
+
+fn read_params(path: &Path) -> Result<Parameters<Bls12>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    // The files are written by this store, so skip the (slow) subgroup checks
+    Ok(Parameters::read(BufReader::new(file), false)?)
+}
+
+// Writes through a temporary file and renames it into place, so a crash or a
+// concurrent reader never sees a half-written key. The temporary name is
+// unique per write, so threads storing the same key don't share it.
+fn write_atomically<F>(path: &Path, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+{
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+    let tmp = path.with_extension(format!(
+        "{}.tmp{}-{}",
+        path.extension().and_then(|e| e.to_str()).unwrap_or(""),
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    write(&mut writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&tmp, path)?;
+
+    Ok(())
+}
+
+// Checks that `params` were generated for a circuit of the measured shape.
+// Key generation adds one constraint per public input (including ONE) and
+// pads the total to a power of two, which sets the size of the H query.
+pub fn check_params(params: &Parameters<Bls12>, metrics: &CircuitMetrics) -> Result<(), String> {
+    let expected_ic = metrics.num_inputs + 1;
+    if params.vk.ic.len() != expected_ic {
+        return Err(format!(
+            "parameters have {} public inputs, circuit has {}",
+            params.vk.ic.len() - 1,
+            metrics.num_inputs
+        ));
+    }
+
+    if params.l.len() != metrics.num_aux {
+        return Err(format!(
+            "parameters have {} auxiliary variables, circuit has {}",
+            params.l.len(),
+            metrics.num_aux
+        ));
+    }
+
+    let domain_size = (metrics.num_constraints + expected_ic).next_power_of_two();
+    if params.h.len() != domain_size - 1 {
+        return Err(format!(
+            "parameters fit a domain of {}, circuit with {} constraints needs {}",
+            params.h.len() + 1,
+            metrics.num_constraints,
+            domain_size
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::fibonacci::FibonacciCircuit;
+    use bellman::{ConstraintSystem, SynthesisError};
+
+    fn blank(n: usize) -> FibonacciCircuit<Fr> {
+        FibonacciCircuit {
+            f0: None,
+            f1: None,
+            fn_val: None,
+            n,
+        }
+    }
+
+    fn vk_bytes(vk: &VerifyingKey<Bls12>) -> Vec<u8> {
+        let mut bytes = vec![];
+        vk.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn temp_store(name: &str) -> ParamStore {
+        let dir = std::env::temp_dir()
+            .join(format!("param_store_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ParamStore::new(dir)
+    }
+
+    #[test]
+    fn parameters_are_reused_across_loads() {
+        let store = temp_store("reuse");
+
+        let generated = store.load_or_generate("fibonacci", "n10", blank(10)).unwrap();
+        let loaded = store.load_or_generate("fibonacci", "n10", blank(10)).unwrap();
+        let vk = store.load_verifying_key("fibonacci", "n10").unwrap();
+
+        assert_eq!(vk_bytes(&generated.vk), vk_bytes(&loaded.vk));
+        assert_eq!(vk_bytes(&generated.vk), vk_bytes(&vk));
+
+        fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn stale_parameters_are_rejected() {
+        let store = temp_store("stale");
+
+        // Parameters for n = 10 stored under the key of a larger circuit
+        let params = store.generate("fibonacci", "n40", blank(10)).unwrap();
+        assert!(store.load_or_generate("fibonacci", "n40", blank(40)).is_err());
+        assert!(check_params(&params, &measure_circuit(blank(10)).unwrap().0).is_ok());
+
+        fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    // x * coefficient = y, so every coefficient gives the same shape
+    #[derive(Clone)]
+    struct Scaled(u64);
+
+    impl Circuit<Fr> for Scaled {
+        fn synthesize<CS: ConstraintSystem<Fr>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = cs.alloc(|| "x", || Err(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(|| "y", || Err(SynthesisError::AssignmentMissing))?;
+            cs.enforce(
+                || "scaled",
+                |lc| lc + (Fr::from(self.0), x),
+                |lc| lc + CS::one(),
+                |lc| lc + y,
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn changed_circuits_of_the_same_shape_are_rejected() {
+        let store = temp_store("fingerprint");
+
+        store.generate("scaled", "", Scaled(2)).unwrap();
+        assert!(store.load_or_generate("scaled", "", Scaled(2)).is_ok());
+        let error = match store.load_or_generate("scaled", "", Scaled(3)) {
+            Ok(_) => panic!("loaded parameters of another circuit"),
+            Err(e) => e.to_string(),
+        };
+        assert!(error.contains("another version of the circuit"), "{}", error);
+
+        // Files from before fingerprints were stored don't pass either
+        fs::remove_file(store.fingerprint_path("scaled", "")).unwrap();
+        assert!(store.load_or_generate("scaled", "", Scaled(2)).is_err());
+
+        fs::remove_dir_all(&store.dir).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+
+// Where and how a benchmark run was produced. Each run is appended as one JSON
+// object per line to a sidecar next to its results CSV, and every CSV row
+// carries the run id.
+#[derive(Clone, Debug)]
+pub struct RunMetadata {
+    pub run_id: String,
+    pub timestamp: u64, // seconds since the Unix epoch
+    pub git_commit: Option<String>,
+    pub git_dirty: Option<bool>,
+    pub cpu_model: Option<String>,
+    pub cores: usize,
+    pub rayon_threads: usize,
+    pub build_profile: &'static str,
+    pub mode: Option<String>, // how the benchmark scheduled its proofs
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+impl RunMetadata {
+    pub fn collect() -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        RunMetadata {
+            // Time first, so run ids sort chronologically
+            run_id: format!("{:x}-{:08x}", timestamp, rand::thread_rng().gen::<u32>()),
+            timestamp,
+            git_commit: git(&["rev-parse", "HEAD"]),
+            git_dirty: git(&["status", "--porcelain", "--untracked-files=no"])
+                .map(|status| !status.is_empty()),
+            cpu_model: cpu_model(),
+            cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            rayon_threads: rayon::current_num_threads(),
+            build_profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            mode: None,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let string = |s: &Option<String>| s.as_deref().map_or("null".to_string(), json_string);
+
+        format!(
+            "{{\"run_id\":{},\"timestamp\":{},\"time\":{},\"git_commit\":{},\"git_dirty\":{},\
+             \"cpu_model\":{},\"cores\":{},\"rayon_threads\":{},\"build_profile\":{},\
+             \"mode\":{},\"os\":{},\"arch\":{}}}",
+            json_string(&self.run_id),
+            self.timestamp,
+            json_string(&utc_time(self.timestamp)),
+            string(&self.git_commit),
+            self.git_dirty.map_or("null".to_string(), |d| d.to_string()),
+            string(&self.cpu_model),
+            self.cores,
+            self.rayon_threads,
+            json_string(self.build_profile),
+            string(&self.mode),
+            json_string(self.os),
+            json_string(self.arch)
+        )
+    }
+
+    // Appends this run to the sidecar of `csv_path`
+    pub fn record(&self, csv_path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(sidecar_path(csv_path))?;
+        writeln!(file, "{}", self.to_json())?;
+        Ok(())
+    }
+}
+
+// results.csv -> results_runs.jsonl
+pub fn sidecar_path(csv_path: &Path) -> PathBuf {
+    let stem = csv_path.file_stem().and_then(|s| s.to_str()).unwrap_or("results");
+    csv_path.with_file_name(format!("{}_runs.jsonl", stem))
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// ISO 8601 UTC time of a Unix timestamp, using the days-to-civil conversion
+// from Howard Hinnant's date algorithms
+fn utc_time(timestamp: u64) -> String {
+    let (days, seconds) = ((timestamp / 86400) as i64, timestamp % 86400);
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_formatted_in_utc() {
+        assert_eq!(utc_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_time(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(utc_time(1791234567), "2026-10-05T21:09:27Z");
+    }
+
+    #[test]
+    fn metadata_is_written_as_one_json_line() {
+        let mut run = RunMetadata::collect();
+        run.cpu_model = Some("CPU \"X\" @ 3GHz".into());
+        run.git_commit = None;
+
+        let json = run.to_json();
+        assert!(!json.contains('\n'));
+        assert!(json.contains(&format!("\"run_id\":\"{}\"", run.run_id)));
+        assert!(json.contains("\"cpu_model\":\"CPU \\\"X\\\" @ 3GHz\""));
+        assert!(json.contains("\"git_commit\":null"));
+        assert_eq!(
+            sidecar_path(Path::new("out/results.csv")),
+            PathBuf::from("out/results_runs.jsonl")
+        );
+    }
+}
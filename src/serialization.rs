@@ -0,0 +1,337 @@
+use std::io::{self, Read};
+
+use bellman::groth16::{Proof, VerifyingKey};
+use bls12_381::{Bls12, G1Affine, G2Affine, Scalar as Fr};
+use ff::PrimeField;
+
+// How curve points are written. Compressed points are half the size but
+// need a square root to decode; a compressed proof is 192 bytes, an
+// uncompressed one 384.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointEncoding {
+    Compressed,
+    Uncompressed,
+}
+
+impl PointEncoding {
+    fn tag(self) -> u8 {
+        match self {
+            PointEncoding::Compressed => 0,
+            PointEncoding::Uncompressed => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(PointEncoding::Compressed),
+            1 => Ok(PointEncoding::Uncompressed),
+            _ => Err(invalid_data(format!("unknown point encoding {}", tag))),
+        }
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn write_g1(out: &mut Vec<u8>, p: &G1Affine, encoding: PointEncoding) {
+    match encoding {
+        PointEncoding::Compressed => out.extend_from_slice(&p.to_compressed()),
+        PointEncoding::Uncompressed => out.extend_from_slice(&p.to_uncompressed()),
+    }
+}
+
+fn write_g2(out: &mut Vec<u8>, p: &G2Affine, encoding: PointEncoding) {
+    match encoding {
+        PointEncoding::Compressed => out.extend_from_slice(&p.to_compressed()),
+        PointEncoding::Uncompressed => out.extend_from_slice(&p.to_uncompressed()),
+    }
+}
+
+fn read_g1<R: Read>(reader: &mut R, encoding: PointEncoding) -> io::Result<G1Affine> {
+    let point = match encoding {
+        PointEncoding::Compressed => {
+            let mut bytes = [0u8; 48];
+            reader.read_exact(&mut bytes)?;
+            G1Affine::from_compressed(&bytes)
+        }
+        PointEncoding::Uncompressed => {
+            let mut bytes = [0u8; 96];
+            reader.read_exact(&mut bytes)?;
+            G1Affine::from_uncompressed(&bytes)
+        }
+    };
+    Option::from(point).ok_or_else(|| invalid_data("invalid G1 point"))
+}
+
+fn read_g2<R: Read>(reader: &mut R, encoding: PointEncoding) -> io::Result<G2Affine> {
+    let point = match encoding {
+        PointEncoding::Compressed => {
+            let mut bytes = [0u8; 96];
+            reader.read_exact(&mut bytes)?;
+            G2Affine::from_compressed(&bytes)
+        }
+        PointEncoding::Uncompressed => {
+            let mut bytes = [0u8; 192];
+            reader.read_exact(&mut bytes)?;
+            G2Affine::from_uncompressed(&bytes)
+        }
+    };
+    Option::from(point).ok_or_else(|| invalid_data("invalid G2 point"))
+}
+
+fn non_identity<P>(point: P, is_identity: bool) -> io::Result<P> {
+    if is_identity {
+        Err(invalid_data("point at infinity"))
+    } else {
+        Ok(point)
+    }
+}
+
+// Fails if anything is left after decoding, so truncated or padded input
+// isn't silently accepted
+fn expect_end(rest: &[u8]) -> io::Result<()> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_data(format!("{} trailing bytes", rest.len())))
+    }
+}
+
+// Proof as A (G1), B (G2), C (G1)
+pub fn encode_proof(proof: &Proof<Bls12>, encoding: PointEncoding) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_g1(&mut bytes, &proof.a, encoding);
+    write_g2(&mut bytes, &proof.b, encoding);
+    write_g1(&mut bytes, &proof.c, encoding);
+    bytes
+}
+
+pub fn decode_proof(bytes: &[u8], encoding: PointEncoding) -> io::Result<Proof<Bls12>> {
+    let mut reader = bytes;
+    let a = read_g1(&mut reader, encoding)?;
+    let b = read_g2(&mut reader, encoding)?;
+    let c = read_g1(&mut reader, encoding)?;
+    expect_end(reader)?;
+
+    // A valid proof never contains the identity, as in `Proof::read`
+    Ok(Proof {
+        a: non_identity(a, a.is_identity().into())?,
+        b: non_identity(b, b.is_identity().into())?,
+        c: non_identity(c, c.is_identity().into())?,
+    })
+}
+
+// Same layout as `VerifyingKey::write`, with a choice of point encoding:
+// alpha_g1, beta_g1, beta_g2, gamma_g2, delta_g1, delta_g2, then the IC
+// query prefixed by its length as a big-endian u32
+pub fn encode_verifying_key(vk: &VerifyingKey<Bls12>, encoding: PointEncoding) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_g1(&mut bytes, &vk.alpha_g1, encoding);
+    write_g1(&mut bytes, &vk.beta_g1, encoding);
+    write_g2(&mut bytes, &vk.beta_g2, encoding);
+    write_g2(&mut bytes, &vk.gamma_g2, encoding);
+    write_g1(&mut bytes, &vk.delta_g1, encoding);
+    write_g2(&mut bytes, &vk.delta_g2, encoding);
+    bytes.extend_from_slice(&(vk.ic.len() as u32).to_be_bytes());
+    for ic in &vk.ic {
+        write_g1(&mut bytes, ic, encoding);
+    }
+    bytes
+}
+
+pub fn decode_verifying_key(
+    bytes: &[u8],
+    encoding: PointEncoding,
+) -> io::Result<VerifyingKey<Bls12>> {
+    let mut reader = bytes;
+    let alpha_g1 = read_g1(&mut reader, encoding)?;
+    let beta_g1 = read_g1(&mut reader, encoding)?;
+    let beta_g2 = read_g2(&mut reader, encoding)?;
+    let gamma_g2 = read_g2(&mut reader, encoding)?;
+    let delta_g1 = read_g1(&mut reader, encoding)?;
+    let delta_g2 = read_g2(&mut reader, encoding)?;
+
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let ic = (0..u32::from_be_bytes(len))
+        .map(|_| {
+            let p = read_g1(&mut reader, encoding)?;
+            non_identity(p, p.is_identity().into())
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    expect_end(reader)?;
+
+    Ok(VerifyingKey {
+        alpha_g1,
+        beta_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g1,
+        delta_g2,
+        ic,
+    })
+}
+
+// Public inputs as consecutive 32-byte little-endian scalars
+pub fn encode_inputs(inputs: &[Fr]) -> Vec<u8> {
+    inputs.iter().flat_map(|x| x.to_repr()).collect()
+}
+
+pub fn decode_inputs(bytes: &[u8]) -> io::Result<Vec<Fr>> {
+    if !bytes.len().is_multiple_of(32) {
+        return Err(invalid_data("public inputs are not a multiple of 32 bytes"));
+    }
+
+    bytes
+        .chunks_exact(32)
+        .map(|chunk| {
+            let mut repr = [0u8; 32];
+            repr.copy_from_slice(chunk);
+            Option::from(Fr::from_repr(repr)).ok_or_else(|| invalid_data("non-canonical scalar"))
+        })
+        .collect()
+}
+
+const ENVELOPE_VERSION: u8 = 1;
+
+// A self-describing proof artifact: which circuit it is for, the public
+// inputs it was proven against, and the proof itself
+pub struct ProofEnvelope {
+    pub circuit_id: String,
+    pub public_inputs: Vec<Fr>,
+    pub proof: Proof<Bls12>,
+}
+
+impl ProofEnvelope {
+    // bincode encoding of (version, point encoding, circuit id, inputs, proof)
+    pub fn to_bytes(&self, encoding: PointEncoding) -> io::Result<Vec<u8>> {
+        bincode::serialize(&(
+            ENVELOPE_VERSION,
+            encoding.tag(),
+            &self.circuit_id,
+            encode_inputs(&self.public_inputs),
+            encode_proof(&self.proof, encoding),
+        ))
+        .map_err(invalid_data)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let (version, tag, circuit_id, inputs, proof): (u8, u8, String, Vec<u8>, Vec<u8>) =
+            bincode::deserialize(bytes).map_err(invalid_data)?;
+
+        if version != ENVELOPE_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported envelope version {}",
+                version
+            )));
+        }
+        let encoding = PointEncoding::from_tag(tag)?;
+
+        Ok(ProofEnvelope {
+            circuit_id,
+            public_inputs: decode_inputs(&inputs)?,
+            proof: decode_proof(&proof, encoding)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::fibonacci::FibonacciCircuit;
+    use bellman::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+        Parameters,
+    };
+    use rand::thread_rng;
+
+    const ENCODINGS: [PointEncoding; 2] = [PointEncoding::Compressed, PointEncoding::Uncompressed];
+
+    fn setup() -> (Parameters<Bls12>, Proof<Bls12>) {
+        let rng = &mut thread_rng();
+        let blank = FibonacciCircuit::<Fr> {
+            f0: None,
+            f1: None,
+            fn_val: None,
+            n: 10,
+        };
+        let params = generate_random_parameters::<Bls12, _, _>(blank, rng).unwrap();
+
+        let c = FibonacciCircuit {
+            f0: Some(Fr::from(0)),
+            f1: Some(Fr::from(1)),
+            fn_val: Some(Fr::from(55)),
+            n: 10,
+        };
+        let proof = create_random_proof(c, &params, rng).unwrap();
+        (params, proof)
+    }
+
+    #[test]
+    fn proof_round_trip() {
+        let (_, proof) = setup();
+        for (encoding, size) in ENCODINGS.into_iter().zip([192, 384]) {
+            let bytes = encode_proof(&proof, encoding);
+            assert_eq!(bytes.len(), size);
+            assert!(decode_proof(&bytes, encoding).unwrap() == proof);
+        }
+    }
+
+    #[test]
+    fn verifying_key_round_trip() {
+        let (params, _) = setup();
+        for encoding in ENCODINGS {
+            let bytes = encode_verifying_key(&params.vk, encoding);
+            assert!(decode_verifying_key(&bytes, encoding).unwrap() == params.vk);
+        }
+
+        // The uncompressed form is exactly what bellman writes
+        let mut written = vec![];
+        params.vk.write(&mut written).unwrap();
+        assert_eq!(
+            encode_verifying_key(&params.vk, PointEncoding::Uncompressed),
+            written
+        );
+    }
+
+    #[test]
+    fn inputs_round_trip() {
+        let inputs = vec![Fr::from(55), -Fr::from(1), Fr::from(0)];
+        assert_eq!(decode_inputs(&encode_inputs(&inputs)).unwrap(), inputs);
+        assert!(decode_inputs(&[0xff; 32]).is_err());
+        assert!(decode_inputs(&[0; 31]).is_err());
+    }
+
+    #[test]
+    fn envelope_round_trip_still_verifies() {
+        let (params, proof) = setup();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        for encoding in ENCODINGS {
+            let envelope = ProofEnvelope {
+                circuit_id: "fibonacci_n10".into(),
+                public_inputs: vec![Fr::from(55)],
+                proof: proof.clone(),
+            };
+            let decoded = ProofEnvelope::from_bytes(&envelope.to_bytes(encoding).unwrap()).unwrap();
+
+            assert_eq!(decoded.circuit_id, "fibonacci_n10");
+            assert!(verify_proof(&pvk, &decoded.proof, &decoded.public_inputs).is_ok());
+        }
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        let (_, proof) = setup();
+        let bytes = encode_proof(&proof, PointEncoding::Compressed);
+
+        assert!(decode_proof(&bytes[..191], PointEncoding::Compressed).is_err());
+        assert!(decode_proof(&[&bytes[..], &[0]].concat(), PointEncoding::Compressed).is_err());
+        assert!(decode_proof(&bytes, PointEncoding::Uncompressed).is_err());
+
+        let mut corrupted = bytes.clone();
+        corrupted[10] ^= 0xff;
+        assert!(decode_proof(&corrupted, PointEncoding::Compressed).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use ff::PrimeField;
+
+// A constraint system that only evaluates the witness: every allocation runs
+// its closure and stores the value, constraints are dropped. Timing it isolates
+// the witness-synthesis part of proving from the multi-exponentiations.
+pub struct WitnessGenerator<F: PrimeField> {
+    pub inputs: Vec<F>, // starts with ONE
+    pub aux: Vec<F>,
+}
+
+impl<F: PrimeField> Default for WitnessGenerator<F> {
+    fn default() -> Self {
+        WitnessGenerator {
+            inputs: vec![F::ONE],
+            aux: vec![],
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for WitnessGenerator<F> {
+    type Root = Self;
+
+    fn alloc<V, A, AR>(&mut self, _: A, f: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f()?);
+
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<V, A, AR>(&mut self, _: A, f: V) -> Result<Variable, SynthesisError>
+    where
+        V: FnOnce() -> Result<F, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f()?);
+
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, _: LA, _: LB, _: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LB: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+        LC: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+    {
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// Synthesizes `circuit` and returns its full assignment. Fails with
+// `AssignmentMissing` if the circuit has no witness.
+pub fn generate_witness<F: PrimeField, C: Circuit<F>>(
+    circuit: C,
+) -> Result<WitnessGenerator<F>, SynthesisError> {
+    let mut cs = WitnessGenerator::default();
+    circuit.synthesize(&mut cs)?;
+    Ok(cs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::fibonacci::FibonacciCircuit;
+    use bls12_381::Scalar as Fr;
+
+    #[test]
+    fn assignment_matches_the_witness() {
+        let witness = generate_witness(FibonacciCircuit {
+            f0: Some(Fr::from(0)),
+            f1: Some(Fr::from(1)),
+            fn_val: Some(Fr::from(55)),
+            n: 10,
+        })
+        .unwrap();
+        assert_eq!(witness.inputs, vec![Fr::from(1), Fr::from(55)]);
+
+        let blank = FibonacciCircuit::<Fr> {
+            f0: None,
+            f1: None,
+            fn_val: None,
+            n: 10,
+        };
+        assert!(matches!(
+            generate_witness(blank),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+}